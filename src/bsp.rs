@@ -12,16 +12,71 @@ use crate::{
     util::lump_ref,
 };
 
+pub mod adjacency;
+pub mod bake;
+pub mod entities;
+pub mod hull;
+pub mod lightstyle;
+pub mod nav;
+pub mod query;
+pub mod raycast;
+pub mod render;
+pub mod stats;
+pub mod surface;
+pub mod texture_name;
+pub mod vis;
+pub mod warp;
+
 /// BSP version (GoldSrc/Quake 1 format).
 pub const BSP_VERSION: u32 = 30;
 /// Number of lumps in a BSP header.
 pub const BSP_LUMPS: usize = 15;
 
+/// Lump indices in `LevelHeader::lumps`.
+pub const LUMP_ENTITIES: usize = 0;
+pub const LUMP_PLANES: usize = 1;
+pub const LUMP_TEXTURES: usize = 2;
+pub const LUMP_VERTICES: usize = 3;
+pub const LUMP_VISIBILITY: usize = 4;
+pub const LUMP_NODES: usize = 5;
+pub const LUMP_TEXINFO: usize = 6;
+pub const LUMP_FACES: usize = 7;
+pub const LUMP_LIGHTING: usize = 8;
+pub const LUMP_CLIPNODES: usize = 9;
+pub const LUMP_LEAVES: usize = 10;
+pub const LUMP_MARKSURFACES: usize = 11;
+pub const LUMP_EDGES: usize = 12;
+pub const LUMP_SURFEDGES: usize = 13;
+pub const LUMP_MODELS: usize = 14;
+
+/// Leaf contents values.
+pub const CONTENTS_EMPTY: i32 = -1;
+pub const CONTENTS_SOLID: i32 = -2;
+pub const CONTENTS_WATER: i32 = -3;
+pub const CONTENTS_SLIME: i32 = -4;
+pub const CONTENTS_LAVA: i32 = -5;
+pub const CONTENTS_SKY: i32 = -6;
+pub const CONTENTS_ORIGIN: i32 = -7;
+pub const CONTENTS_CLIP: i32 = -8;
+pub const CONTENTS_CURRENT_0: i32 = -9;
+pub const CONTENTS_CURRENT_90: i32 = -10;
+pub const CONTENTS_CURRENT_180: i32 = -11;
+pub const CONTENTS_CURRENT_270: i32 = -12;
+pub const CONTENTS_CURRENT_UP: i32 = -13;
+pub const CONTENTS_CURRENT_DOWN: i32 = -14;
+pub const CONTENTS_TRANSLUCENT: i32 = -15;
+pub const CONTENTS_LADDER: i32 = -16;
+
+/// Texture info flag marking sky and liquid surfaces (no lightmap).
+pub const TEXTURE_SPECIAL: u32 = 1;
+
 /// Edge represented as two vertex indices.
 pub type Edge = [U16; 2];
 
 /// Complete level data loaded from a BSP file.
 pub struct Level<'a> {
+    /// File header with the lump table.
+    pub header: &'a LevelHeader,
     /// List of entities in the level.
     pub entities: &'a [u8],
     /// Planes used for spatial partitioning.
@@ -92,6 +147,15 @@ pub struct Node {
     pub faces_num: U16,
 }
 
+/// Child of a BSP node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeChild {
+    /// Index into the nodes array.
+    Node(usize),
+    /// Index into the leaves array.
+    Leaf(usize),
+}
+
 /// Texture mapping info for a face.
 #[repr(C)]
 #[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
@@ -176,6 +240,28 @@ pub struct Model {
     pub faces_num: U32,
 }
 
+impl Plane {
+    /// Signed distance from the plane to a point.
+    pub fn distance_to(&self, point: [f32; 3]) -> f32 {
+        self.normal[0].get() * point[0]
+            + self.normal[1].get() * point[1]
+            + self.normal[2].get() * point[2]
+            - self.distance.get()
+    }
+}
+
+impl Node {
+    /// Front (`side == 0`) or back (`side == 1`) child of the node.
+    pub fn child(&self, side: usize) -> NodeChild {
+        let child = self.children[side].get();
+        if child >= 0 {
+            NodeChild::Node(child as usize)
+        } else {
+            NodeChild::Leaf(!child as usize)
+        }
+    }
+}
+
 pub fn level(bytes: &[u8]) -> ParsingResult<Level<'_>> {
     let (header, _) =
         LevelHeader::ref_from_prefix(bytes).map_err(|_| ParsingError::OutOfRange("bsp header"))?;
@@ -189,21 +275,26 @@ pub fn level(bytes: &[u8]) -> ParsingResult<Level<'_>> {
     }
 
     Ok(Level {
-        entities: lump_ref(bytes, &header.lumps[0], "bsp entities")?,
-        planes: lump_ref(bytes, &header.lumps[1], "bsp planes")?,
-        textures: miptex_lump(lump_ref::<u8>(bytes, &header.lumps[2], "bsp textures")?)?,
-        vertices: lump_ref(bytes, &header.lumps[3], "bsp vertices")?,
-        visdata: lump_ref(bytes, &header.lumps[4], "bsp visdata")?,
-        nodes: lump_ref(bytes, &header.lumps[5], "bsp nodes")?,
-        texture_infos: lump_ref(bytes, &header.lumps[6], "bsp texture infos")?,
-        faces: lump_ref(bytes, &header.lumps[7], "bsp faces")?,
-        lighting: lump_ref(bytes, &header.lumps[8], "bsp lighting")?,
-        clip_nodes: lump_ref(bytes, &header.lumps[9], "bsp clip nodes")?,
-        leaves: lump_ref(bytes, &header.lumps[10], "bsp leaves")?,
-        mark_surfaces: lump_ref(bytes, &header.lumps[11], "bsp mark surfaces")?,
-        edges: lump_ref(bytes, &header.lumps[12], "bsp edges")?,
-        surfedges: lump_ref(bytes, &header.lumps[13], "bsp surfedges")?,
-        models: lump_ref(bytes, &header.lumps[14], "bsp models")?,
+        header,
+        entities: lump_ref(bytes, &header.lumps[LUMP_ENTITIES], "bsp entities")?,
+        planes: lump_ref(bytes, &header.lumps[LUMP_PLANES], "bsp planes")?,
+        textures: miptex_lump(lump_ref::<u8>(
+            bytes,
+            &header.lumps[LUMP_TEXTURES],
+            "bsp textures",
+        )?)?,
+        vertices: lump_ref(bytes, &header.lumps[LUMP_VERTICES], "bsp vertices")?,
+        visdata: lump_ref(bytes, &header.lumps[LUMP_VISIBILITY], "bsp visdata")?,
+        nodes: lump_ref(bytes, &header.lumps[LUMP_NODES], "bsp nodes")?,
+        texture_infos: lump_ref(bytes, &header.lumps[LUMP_TEXINFO], "bsp texture infos")?,
+        faces: lump_ref(bytes, &header.lumps[LUMP_FACES], "bsp faces")?,
+        lighting: lump_ref(bytes, &header.lumps[LUMP_LIGHTING], "bsp lighting")?,
+        clip_nodes: lump_ref(bytes, &header.lumps[LUMP_CLIPNODES], "bsp clip nodes")?,
+        leaves: lump_ref(bytes, &header.lumps[LUMP_LEAVES], "bsp leaves")?,
+        mark_surfaces: lump_ref(bytes, &header.lumps[LUMP_MARKSURFACES], "bsp mark surfaces")?,
+        edges: lump_ref(bytes, &header.lumps[LUMP_EDGES], "bsp edges")?,
+        surfedges: lump_ref(bytes, &header.lumps[LUMP_SURFEDGES], "bsp surfedges")?,
+        models: lump_ref(bytes, &header.lumps[LUMP_MODELS], "bsp models")?,
     })
 }
 
@@ -0,0 +1,172 @@
+use crate::{
+    bsp::Level,
+    error::{ParsingError, ParsingResult},
+    math::{dot, sub, vec3},
+};
+
+/// Distance under which a vertex is considered lying on an edge.
+pub const T_JUNCTION_EPSILON: f32 = 0.1;
+
+/// Use of an edge by a face.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeUse {
+    /// Index of the face.
+    pub face_id: usize,
+    /// Whether the face walks the edge from its second vertex to its first
+    /// (negative surfedge).
+    pub reversed: bool,
+}
+
+/// Vertex lying inside an edge without splitting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TJunction {
+    /// Index of the edge.
+    pub edge_id: usize,
+    /// Index of the vertex lying on the edge.
+    pub vertex_id: usize,
+}
+
+/// Adjacency of faces through shared edges.
+#[derive(Debug, Clone)]
+pub struct FaceGraph {
+    /// Faces using each edge, indexed like `Level::edges`.
+    pub edge_faces: Vec<Vec<EdgeUse>>,
+    /// Sorted neighbour faces of each face, indexed like `Level::faces`.
+    pub face_neighbors: Vec<Vec<usize>>,
+    /// Edges used by exactly one face.
+    pub boundary_edges: Vec<usize>,
+    /// Vertices lying inside boundary edges.
+    pub t_junctions: Vec<TJunction>,
+}
+
+impl FaceGraph {
+    /// Builds the graph over every face of the level.
+    pub fn new(level: &Level<'_>) -> ParsingResult<Self> {
+        let mut edge_faces = vec![Vec::new(); level.edges.len()];
+        for (face_id, face) in level.faces.iter().enumerate() {
+            let first = face.first_surfedge_id.get() as usize;
+            let count = usize::from(face.surfedges_num.get());
+            let surfedges = level
+                .surfedges
+                .get(first..first + count)
+                .ok_or(ParsingError::OutOfRange("bsp face surfedges"))?;
+
+            for surfedge in surfedges {
+                let surfedge = surfedge.get();
+                edge_faces
+                    .get_mut(surfedge.unsigned_abs() as usize)
+                    .ok_or(ParsingError::OutOfRange("bsp face edge"))?
+                    .push(EdgeUse {
+                        face_id,
+                        reversed: surfedge < 0,
+                    });
+            }
+        }
+
+        let mut face_neighbors = vec![Vec::new(); level.faces.len()];
+        let mut boundary_edges = Vec::new();
+        for (edge_id, uses) in edge_faces.iter().enumerate() {
+            if uses.len() == 1 {
+                boundary_edges.push(edge_id);
+            }
+            for a in uses {
+                for b in uses {
+                    if a.face_id != b.face_id {
+                        face_neighbors[a.face_id].push(b.face_id);
+                    }
+                }
+            }
+        }
+        for neighbors in &mut face_neighbors {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+
+        let t_junctions = find_t_junctions(level, &edge_faces, &boundary_edges)?;
+
+        Ok(Self {
+            edge_faces,
+            face_neighbors,
+            boundary_edges,
+            t_junctions,
+        })
+    }
+
+    /// First faces walking the edge forward and reversed.
+    pub fn edge_pair(&self, edge_id: usize) -> (Option<usize>, Option<usize>) {
+        let Some(uses) = self.edge_faces.get(edge_id) else {
+            return (None, None);
+        };
+        let find = |reversed| {
+            uses.iter()
+                .find(|edge_use| edge_use.reversed == reversed)
+                .map(|edge_use| edge_use.face_id)
+        };
+        (find(false), find(true))
+    }
+}
+
+fn find_t_junctions(
+    level: &Level<'_>,
+    edge_faces: &[Vec<EdgeUse>],
+    boundary_edges: &[usize],
+) -> ParsingResult<Vec<TJunction>> {
+    let vertex = |vertex_id: usize| {
+        level
+            .vertices
+            .get(vertex_id)
+            .map(vec3)
+            .ok_or(ParsingError::OutOfRange("bsp edge vertex"))
+    };
+
+    // Candidates are vertices used by faces, sorted along X for range lookups.
+    let mut candidates = Vec::new();
+    for (edge, uses) in level.edges.iter().zip(edge_faces) {
+        if !uses.is_empty() {
+            for vertex_id in edge {
+                let vertex_id = usize::from(vertex_id.get());
+                candidates.push((vertex(vertex_id)?, vertex_id));
+            }
+        }
+    }
+    candidates.sort_unstable_by(|(a, ai), (b, bi)| a[0].total_cmp(&b[0]).then(ai.cmp(bi)));
+    candidates.dedup_by_key(|(_, vertex_id)| *vertex_id);
+
+    let mut t_junctions = Vec::new();
+    for &edge_id in boundary_edges {
+        let [a_id, b_id] = level.edges[edge_id].map(|id| usize::from(id.get()));
+        let a = vertex(a_id)?;
+        let b = vertex(b_id)?;
+        let dir = sub(b, a);
+        let length_sq = dot(dir, dir);
+        if length_sq <= T_JUNCTION_EPSILON * T_JUNCTION_EPSILON {
+            continue;
+        }
+
+        let min_x = a[0].min(b[0]) - T_JUNCTION_EPSILON;
+        let max_x = a[0].max(b[0]) + T_JUNCTION_EPSILON;
+        let start = candidates.partition_point(|(v, _)| v[0] < min_x);
+        for &(v, vertex_id) in candidates[start..]
+            .iter()
+            .take_while(|(v, _)| v[0] <= max_x)
+        {
+            if vertex_id == a_id || vertex_id == b_id {
+                continue;
+            }
+
+            let offset = sub(v, a);
+            let t = dot(offset, dir) / length_sq;
+            let margin = T_JUNCTION_EPSILON / length_sq.sqrt();
+            if t <= margin || t >= 1.0 - margin {
+                continue;
+            }
+
+            let dist_sq = dot(offset, offset) - t * t * length_sq;
+            if dist_sq <= T_JUNCTION_EPSILON * T_JUNCTION_EPSILON {
+                t_junctions.push(TJunction { edge_id, vertex_id });
+            }
+        }
+    }
+
+    Ok(t_junctions)
+}
@@ -0,0 +1,427 @@
+use zerocopy::{IntoBytes, little_endian::U32};
+
+use crate::{
+    bsp::{
+        CONTENTS_SKY, CONTENTS_SOLID, Face, LUMP_FACES, LUMP_LIGHTING, Level, LevelHeader,
+        TEXTURE_SPECIAL,
+        entities::{Entity, entities},
+        hull::{HULL_POINT, point_contents, trace},
+        level,
+        lightstyle::{MAX_LIGHTMAPS, MAX_LIGHTSTYLES, STYLE_NONE},
+        surface::{face_plane, face_polygon, face_texture_info, lightmap_extents},
+    },
+    error::{ParsingError, ParsingResult},
+    math::{cross, dot, length, lerp, mad, normalize, scale, sub, vec3},
+    util::{parse_f32, to_validate_range},
+};
+
+/// Distance sun visibility is traced to.
+pub const SUN_TRACE_DISTANCE: f32 = 16384.0;
+/// Distance samples are lifted off their surface before tracing.
+const SAMPLE_OFFSET: f32 = 1.0;
+/// Default inner and outer cone angles of `light_spot`, in degrees.
+const DEFAULT_CONE: f32 = 10.0;
+const DEFAULT_CONE2: f32 = 20.0;
+/// `angle` values pointing straight up and down.
+const ANGLE_UP: f32 = -1.0;
+const ANGLE_DOWN: f32 = -2.0;
+
+/// Light type.
+#[derive(Debug, Clone, Copy)]
+pub enum BakeLightKind {
+    /// Omnidirectional light (`light`).
+    Point,
+    /// Cone light (`light_spot`), full intensity inside the inner cone.
+    Spot {
+        /// Direction the light points to.
+        direction: [f32; 3],
+        /// Cosine of the inner cone half-angle.
+        inner_cos: f32,
+        /// Cosine of the outer cone half-angle.
+        outer_cos: f32,
+    },
+    /// Sun light coming through sky surfaces (`light_environment`).
+    Sun {
+        /// Direction the light travels in.
+        direction: [f32; 3],
+    },
+}
+
+/// Light source used by the baker.
+///
+/// Point and spot lights fall off with the inverse square of the distance and
+/// reach their full color at a distance equal to their brightness, so a
+/// `_light "255 255 255 200"` lights a surface 200 units away with white.
+#[derive(Debug, Clone)]
+pub struct BakeLight {
+    /// Light position (unused for sun lights).
+    pub origin: [f32; 3],
+    /// Light color, `255.0` being full intensity.
+    pub color: [f32; 3],
+    /// Brightness, the distance at which the full color is reached.
+    pub brightness: f32,
+    /// Light style the light is baked into.
+    pub style: u8,
+    /// Light type.
+    pub kind: BakeLightKind,
+}
+
+/// Lighting baker options.
+#[derive(Debug, Clone)]
+pub struct BakeOptions {
+    /// Light added to every sample of the style 0 lightmap.
+    pub ambient: [f32; 3],
+}
+
+impl Default for BakeOptions {
+    fn default() -> Self {
+        Self { ambient: [0.0; 3] }
+    }
+}
+
+/// Faces and lighting lump produced by the baker.
+#[derive(Debug, Clone)]
+pub struct BakedLighting {
+    /// Faces with updated styles and lightmap offsets.
+    pub faces: Vec<Face>,
+    /// New lighting lump.
+    pub lighting: Vec<u8>,
+}
+
+/// Parses the entity lump and bakes the level with its lights.
+pub fn bake_level(bytes: &[u8], options: &BakeOptions) -> ParsingResult<Vec<u8>> {
+    let level = level(bytes)?;
+    let lights = bake_lights(&entities(level.entities)?);
+    let baked = bake_lighting(&level, &lights, options)?;
+    write_baked_level(bytes, &baked)
+}
+
+/// Light sources defined by `light`, `light_spot` and `light_environment` entities.
+pub fn bake_lights(entities: &[Entity<'_>]) -> Vec<BakeLight> {
+    let mut lights = Vec::new();
+
+    for entity in entities {
+        let Some(classname) = entity.classname() else {
+            continue;
+        };
+        let Some(origin) = entity.get_vec3(b"origin").or(match classname {
+            b"light_environment" => Some([0.0; 3]),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let (color, brightness) = light_color(entity);
+        let style = entity
+            .get_i32(b"style")
+            .and_then(|style| u8::try_from(style).ok())
+            .filter(|&style| usize::from(style) < MAX_LIGHTSTYLES)
+            .unwrap_or(0);
+
+        let kind = match classname {
+            b"light" => BakeLightKind::Point,
+            b"light_spot" => {
+                let direction = target_direction(entities, entity, origin)
+                    .unwrap_or_else(|| angle_direction(entity));
+                let inner = entity.get_f32(b"_cone").unwrap_or(DEFAULT_CONE);
+                let outer = entity
+                    .get_f32(b"_cone2")
+                    .unwrap_or(DEFAULT_CONE2)
+                    .max(inner);
+                BakeLightKind::Spot {
+                    direction,
+                    inner_cos: inner.to_radians().cos(),
+                    outer_cos: outer.to_radians().cos(),
+                }
+            }
+            b"light_environment" => BakeLightKind::Sun {
+                direction: angle_direction(entity),
+            },
+            _ => continue,
+        };
+
+        lights.push(BakeLight {
+            origin,
+            color,
+            brightness,
+            style,
+            kind,
+        });
+    }
+
+    lights
+}
+
+/// Bakes direct lighting of every face without `TEXTURE_SPECIAL`.
+///
+/// Faces are lit whether they had a lightmap or not, so levels compiled
+/// without RAD get lighting too.
+///
+/// Shadows are traced through hull 0 of the world, so brush entities don't
+/// cast shadows and their faces are lit at their model position.
+pub fn bake_lighting(
+    level: &Level<'_>,
+    lights: &[BakeLight],
+    options: &BakeOptions,
+) -> ParsingResult<BakedLighting> {
+    let mut faces = Vec::with_capacity(level.faces.len());
+    let mut lighting = Vec::new();
+
+    for face in level.faces {
+        let lit = face_texture_info(level, face)?.flags.get() & TEXTURE_SPECIAL == 0;
+        let mut face = face.clone();
+        face.lighting_styles = [STYLE_NONE; MAX_LIGHTMAPS];
+        face.lightmap_offset = U32::new(u32::MAX);
+
+        if lit {
+            let styles = bake_face(level, &face, lights, options)?;
+            face.lightmap_offset = U32::new(
+                u32::try_from(lighting.len())
+                    .map_err(|_| ParsingError::NumberOverflow("bsp lighting"))?,
+            );
+            for (slot, (style, samples)) in styles.into_iter().enumerate() {
+                face.lighting_styles[slot] = style;
+                lighting.extend(
+                    samples
+                        .iter()
+                        .flat_map(|sample| sample.map(|v| v.round().clamp(0.0, 255.0) as u8)),
+                );
+            }
+        }
+
+        faces.push(face);
+    }
+
+    Ok(BakedLighting { faces, lighting })
+}
+
+/// Rebuilds a BSP file with the faces and lighting lumps replaced.
+pub fn write_baked_level(bytes: &[u8], baked: &BakedLighting) -> ParsingResult<Vec<u8>> {
+    let level = level(bytes)?;
+    if baked.faces.len() != level.faces.len() {
+        return Err(ParsingError::Invalid("bsp baked faces count"));
+    }
+
+    let mut header = level.header.clone();
+    let mut out = vec![0; size_of::<LevelHeader>()];
+    for (lump_id, lump) in header.lumps.iter_mut().enumerate() {
+        let data = match lump_id {
+            LUMP_FACES => baked.faces.as_bytes(),
+            LUMP_LIGHTING => baked.lighting.as_slice(),
+            _ => bytes
+                .get(to_validate_range(
+                    lump.offset.get(),
+                    lump.size.get(),
+                    "bsp lump",
+                )?)
+                .ok_or(ParsingError::OutOfRange("bsp lump"))?,
+        };
+
+        out.resize(out.len().next_multiple_of(4), 0);
+        lump.offset = U32::new(
+            u32::try_from(out.len()).map_err(|_| ParsingError::NumberOverflow("bsp lump"))?,
+        );
+        lump.size = U32::new(
+            u32::try_from(data.len()).map_err(|_| ParsingError::NumberOverflow("bsp lump"))?,
+        );
+        out.extend_from_slice(data);
+    }
+    out[..size_of::<LevelHeader>()].copy_from_slice(header.as_bytes());
+
+    Ok(out)
+}
+
+type StyleSamples = Vec<(u8, Vec<[f32; 3]>)>;
+
+fn bake_face(
+    level: &Level<'_>,
+    face: &Face,
+    lights: &[BakeLight],
+    options: &BakeOptions,
+) -> ParsingResult<StyleSamples> {
+    let extents = lightmap_extents(level, face)?;
+    let (normal, distance) = face_plane(level, face)?;
+    let texture_info = face_texture_info(level, face)?;
+    let s = vec3(&texture_info.s);
+    let t = vec3(&texture_info.t);
+    let det = dot(s, cross(t, normal));
+
+    let polygon = face_polygon(level, face)?;
+    let center = scale(
+        polygon.iter().fold([0.0; 3], |acc, &v| mad(acc, v, 1.0)),
+        1.0 / polygon.len().max(1) as f32,
+    );
+    let center = mad(center, normal, SAMPLE_OFFSET);
+
+    let mut styles: StyleSamples = vec![(0, vec![options.ambient; extents.samples()])];
+    if det.abs() < f32::EPSILON {
+        return Ok(styles);
+    }
+
+    for (sample_id, (row, column)) in (0..extents.size[1])
+        .flat_map(|row| (0..extents.size[0]).map(move |column| (row, column)))
+        .enumerate()
+    {
+        // Solve the texture mapping for the world position of the sample.
+        let u = (extents.mins[0] + column as i32 * 16) as f32 - texture_info.s_shift.get();
+        let v = (extents.mins[1] + row as i32 * 16) as f32 - texture_info.t_shift.get();
+        let point = scale(
+            mad(
+                mad(scale(cross(t, normal), u), cross(normal, s), v),
+                cross(s, t),
+                distance,
+            ),
+            1.0 / det,
+        );
+        let point = sample_point(level, mad(point, normal, SAMPLE_OFFSET), center)?;
+
+        for light in lights {
+            let add = light_sample(level, light, point, normal)?;
+            if add.iter().all(|&v| v <= 0.0) {
+                continue;
+            }
+
+            let slot = match styles.iter().position(|(style, _)| *style == light.style) {
+                Some(slot) => slot,
+                None if styles.len() < MAX_LIGHTMAPS => {
+                    styles.push((light.style, vec![[0.0; 3]; extents.samples()]));
+                    styles.len() - 1
+                }
+                None => continue,
+            };
+            let sample = &mut styles[slot].1[sample_id];
+            *sample = mad(*sample, add, 1.0);
+        }
+    }
+
+    Ok(styles)
+}
+
+/// Moves samples lying in solid (outside of the face) towards its center.
+fn sample_point(level: &Level<'_>, point: [f32; 3], center: [f32; 3]) -> ParsingResult<[f32; 3]> {
+    for step in 0..4 {
+        let candidate = lerp(point, center, step as f32 / 4.0);
+        if point_contents(level, 0, HULL_POINT, candidate)? != CONTENTS_SOLID {
+            return Ok(candidate);
+        }
+    }
+
+    Ok(center)
+}
+
+fn light_sample(
+    level: &Level<'_>,
+    light: &BakeLight,
+    point: [f32; 3],
+    normal: [f32; 3],
+) -> ParsingResult<[f32; 3]> {
+    let (intensity, shadow_end) = match light.kind {
+        BakeLightKind::Sun { direction } => {
+            let cos = -dot(normal, direction);
+            if cos <= 0.0 {
+                return Ok([0.0; 3]);
+            }
+            let end = mad(point, direction, -SUN_TRACE_DISTANCE);
+            let hit = trace(level, 0, HULL_POINT, point, end)?;
+            let sees_sky = hit.fraction < 1.0
+                && point_contents(level, 0, HULL_POINT, hit.end_pos)? == CONTENTS_SKY;
+            return Ok(if sees_sky {
+                scale(light.color, cos)
+            } else {
+                [0.0; 3]
+            });
+        }
+        BakeLightKind::Point => (1.0, light.origin),
+        BakeLightKind::Spot {
+            direction,
+            inner_cos,
+            outer_cos,
+        } => {
+            let cos = dot(direction, normalize(sub(point, light.origin)));
+            if cos <= outer_cos {
+                return Ok([0.0; 3]);
+            }
+            let intensity = if cos < inner_cos {
+                (cos - outer_cos) / (inner_cos - outer_cos)
+            } else {
+                1.0
+            };
+            (intensity, light.origin)
+        }
+    };
+
+    let delta = sub(shadow_end, point);
+    let dist = length(delta).max(1.0);
+    let cos = dot(normal, delta) / dist;
+    let falloff = (light.brightness / dist).powi(2);
+    let value = intensity * cos * falloff;
+    // Skip traces for contributions below a single color step.
+    if cos <= 0.0 || light.color.iter().all(|&c| c * value < 0.5) {
+        return Ok([0.0; 3]);
+    }
+
+    let hit = trace(level, 0, HULL_POINT, point, shadow_end)?;
+    if hit.start_solid || hit.fraction < 1.0 {
+        return Ok([0.0; 3]);
+    }
+
+    Ok(scale(light.color, value))
+}
+
+/// Color and brightness from `_light` (`r g b brightness`, `r g b` or `brightness`).
+fn light_color(entity: &Entity<'_>) -> ([f32; 3], f32) {
+    let values: Vec<f32> = entity
+        .get(b"_light")
+        .map(|value| {
+            value
+                .split(u8::is_ascii_whitespace)
+                .filter(|v| !v.is_empty())
+                .map_while(parse_f32)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    match values.as_slice() {
+        [r, g, b, brightness, ..] => ([*r, *g, *b], *brightness),
+        [r, g, b] => ([*r, *g, *b], r.max(*g).max(*b)),
+        [brightness] => ([255.0; 3], *brightness),
+        _ => ([255.0; 3], 300.0),
+    }
+}
+
+/// Direction towards the entity named by `target`.
+fn target_direction(
+    entities: &[Entity<'_>],
+    entity: &Entity<'_>,
+    origin: [f32; 3],
+) -> Option<[f32; 3]> {
+    let target_name = entity.get(b"target")?;
+    let target = entities
+        .iter()
+        .find(|other| other.get(b"targetname") == Some(target_name))?;
+    let delta = sub(target.get_vec3(b"origin")?, origin);
+    (length(delta) > 0.0).then(|| normalize(delta))
+}
+
+/// Direction from `angle`/`angles` yaw and `pitch`, negative pitch pointing down.
+fn angle_direction(entity: &Entity<'_>) -> [f32; 3] {
+    let angles = entity.get_vec3(b"angles").unwrap_or([0.0; 3]);
+    let yaw = entity.get_f32(b"angle").unwrap_or(angles[1]);
+    if yaw == ANGLE_UP {
+        return [0.0, 0.0, 1.0];
+    }
+    if yaw == ANGLE_DOWN {
+        return [0.0, 0.0, -1.0];
+    }
+    let pitch = entity
+        .get_f32(b"pitch")
+        .filter(|&pitch| pitch != 0.0)
+        .unwrap_or(angles[0])
+        .to_radians();
+    let yaw = yaw.to_radians();
+
+    [
+        yaw.cos() * pitch.cos(),
+        yaw.sin() * pitch.cos(),
+        pitch.sin(),
+    ]
+}
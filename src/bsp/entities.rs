@@ -0,0 +1,140 @@
+use crate::{
+    error::{ParsingError, ParsingResult},
+    util::parse_f32,
+};
+
+/// Entity from the entity lump.
+#[derive(Debug, Clone, Default)]
+pub struct Entity<'a> {
+    /// Key-value pairs in file order (not guaranteed UTF-8).
+    pub pairs: Vec<(&'a [u8], &'a [u8])>,
+}
+
+impl<'a> Entity<'a> {
+    /// Value of the first pair with the key.
+    pub fn get(&self, key: &[u8]) -> Option<&'a [u8]> {
+        self.pairs
+            .iter()
+            .find_map(|&(k, v)| (k == key).then_some(v))
+    }
+
+    /// Entity class name.
+    pub fn classname(&self) -> Option<&'a [u8]> {
+        self.get(b"classname")
+    }
+
+    /// Value of the key parsed as a float.
+    pub fn get_f32(&self, key: &[u8]) -> Option<f32> {
+        parse_f32(self.get(key)?)
+    }
+
+    /// Value of the key parsed as an integer (fractions are truncated like `atoi`).
+    pub fn get_i32(&self, key: &[u8]) -> Option<i32> {
+        let value = std::str::from_utf8(self.get(key)?).ok()?.trim();
+        value
+            .parse()
+            .ok()
+            .or_else(|| value.parse::<f32>().ok().map(|v| v as i32))
+    }
+
+    /// Value of the key parsed as three space separated floats.
+    pub fn get_vec3(&self, key: &[u8]) -> Option<[f32; 3]> {
+        let mut values = self
+            .get(key)?
+            .split(u8::is_ascii_whitespace)
+            .filter(|v| !v.is_empty());
+        let mut vec = [0.0; 3];
+        for slot in &mut vec {
+            *slot = parse_f32(values.next()?)?;
+        }
+        Some(vec)
+    }
+}
+
+/// Parses the entity lump (`{ "key" "value" ... }` blocks).
+pub fn entities(bytes: &[u8]) -> ParsingResult<Vec<Entity<'_>>> {
+    let mut tokens = Tokenizer { bytes };
+    let mut entities = Vec::new();
+
+    while let Some(token) = tokens.next_token()? {
+        if token != b"{" {
+            return Err(ParsingError::Invalid("bsp entity block"));
+        }
+
+        let mut entity = Entity::default();
+        loop {
+            let key = tokens
+                .next_token()?
+                .ok_or(ParsingError::OutOfRange("bsp entity"))?;
+            if key == b"}" {
+                break;
+            }
+            let value = tokens
+                .next_token()?
+                .ok_or(ParsingError::OutOfRange("bsp entity value"))?;
+            if value == b"}" {
+                return Err(ParsingError::Invalid("bsp entity value"));
+            }
+            entity.pairs.push((key, value));
+        }
+        entities.push(entity);
+    }
+
+    Ok(entities)
+}
+
+struct Tokenizer<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Tokenizer<'a> {
+    // Mirrors `COM_Parse`: quoted strings, single brace tokens, `//` comments.
+    fn next_token(&mut self) -> ParsingResult<Option<&'a [u8]>> {
+        loop {
+            let start = self
+                .bytes
+                .iter()
+                .position(|&b| !b.is_ascii_whitespace() && b != 0)
+                .unwrap_or(self.bytes.len());
+            self.bytes = &self.bytes[start..];
+
+            if self.bytes.starts_with(b"//") {
+                let end = self
+                    .bytes
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .unwrap_or(self.bytes.len());
+                self.bytes = &self.bytes[end..];
+            } else {
+                break;
+            }
+        }
+
+        match self.bytes {
+            [] => Ok(None),
+            [b'"', rest @ ..] => {
+                let end = rest
+                    .iter()
+                    .position(|&b| b == b'"')
+                    .ok_or(ParsingError::Invalid("bsp entity string"))?;
+                self.bytes = &rest[end + 1..];
+                Ok(Some(&rest[..end]))
+            }
+            [b'{' | b'}', ..] => {
+                let (token, rest) = self.bytes.split_at(1);
+                self.bytes = rest;
+                Ok(Some(token))
+            }
+            _ => {
+                let end = self
+                    .bytes
+                    .iter()
+                    .position(|&b| b.is_ascii_whitespace() || b == 0 || b == b'"')
+                    .unwrap_or(self.bytes.len());
+                let (token, rest) = self.bytes.split_at(end);
+                self.bytes = rest;
+                Ok(Some(token))
+            }
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    bsp::{CONTENTS_EMPTY, CONTENTS_SOLID, Level},
+    common::BBox,
+    error::{ParsingError, ParsingResult},
+    math::{dot, lerp, vec3},
+};
+
+/// Number of collision hulls per model.
+pub const MAX_HULLS: usize = 4;
+/// Point hull, traced through the render nodes.
+pub const HULL_POINT: usize = 0;
+/// Standing player hull.
+pub const HULL_HUMAN: usize = 1;
+/// Large monster hull.
+pub const HULL_LARGE: usize = 2;
+/// Crouching player hull.
+pub const HULL_HEAD: usize = 3;
+
+/// Box swept by each hull, relative to the traced origin.
+pub const HULL_SIZES: [BBox<[f32; 3]>; MAX_HULLS] = [
+    BBox {
+        min: [0.0, 0.0, 0.0],
+        max: [0.0, 0.0, 0.0],
+    },
+    BBox {
+        min: [-16.0, -16.0, -36.0],
+        max: [16.0, 16.0, 36.0],
+    },
+    BBox {
+        min: [-32.0, -32.0, -32.0],
+        max: [32.0, 32.0, 32.0],
+    },
+    BBox {
+        min: [-16.0, -16.0, -18.0],
+        max: [16.0, 16.0, 18.0],
+    },
+];
+
+/// Distance kept between a trace end and the surface it hit.
+const DIST_EPSILON: f32 = 0.03125;
+
+/// Result of a hull trace.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    /// Whether the whole trace was in solid.
+    pub all_solid: bool,
+    /// Whether the trace started in solid.
+    pub start_solid: bool,
+    /// Whether the trace passed through empty space.
+    pub in_open: bool,
+    /// Whether the trace passed through non-empty, non-solid space.
+    pub in_water: bool,
+    /// Fraction of the move completed (`1.0` when nothing was hit).
+    pub fraction: f32,
+    /// Final position.
+    pub end_pos: [f32; 3],
+    /// Normal of the hit surface.
+    pub plane_normal: [f32; 3],
+    /// Distance of the hit surface plane.
+    pub plane_distance: f32,
+}
+
+/// Contents of a point in a model hull (`SV_HullPointContents`).
+pub fn point_contents(
+    level: &Level<'_>,
+    model_id: usize,
+    hull: usize,
+    point: [f32; 3],
+) -> ParsingResult<i32> {
+    let hull = Hull::new(level, model_id, hull)?;
+    hull.point_contents(hull.head, point)
+}
+
+/// Traces a point from `start` to `end` through a model hull (`SV_RecursiveHullCheck`).
+///
+/// Hulls other than 0 are expanded by `HULL_SIZES`, so tracing a point
+/// through them is equal to sweeping the hull box.
+pub fn trace(
+    level: &Level<'_>,
+    model_id: usize,
+    hull: usize,
+    start: [f32; 3],
+    end: [f32; 3],
+) -> ParsingResult<Trace> {
+    let hull = Hull::new(level, model_id, hull)?;
+    let mut trace = Trace {
+        all_solid: true,
+        start_solid: false,
+        in_open: false,
+        in_water: false,
+        fraction: 1.0,
+        end_pos: end,
+        plane_normal: [0.0; 3],
+        plane_distance: 0.0,
+    };
+    hull.check_r(hull.head, 0, 0.0, 1.0, start, end, &mut trace)?;
+
+    Ok(trace)
+}
+
+/// Contents of every leaf reachable from a model hull.
+pub fn hull_contents(
+    level: &Level<'_>,
+    model_id: usize,
+    hull: usize,
+    mut f: impl FnMut(i32),
+) -> ParsingResult<()> {
+    let hull = Hull::new(level, model_id, hull)?;
+    let mut stack = vec![hull.head];
+    // A tree of `n` nodes has `2n + 1` references, more means a cycle.
+    let limit = 2 * (level.nodes.len() + level.clip_nodes.len()) + 1;
+    let mut visited = 0usize;
+    while let Some(num) = stack.pop() {
+        match hull.resolve(num)? {
+            HullRef::Contents(contents) => f(contents),
+            HullRef::Node { children, .. } => stack.extend(children),
+        }
+
+        visited += 1;
+        if visited > limit {
+            return Err(ParsingError::Invalid("bsp hull tree"));
+        }
+    }
+
+    Ok(())
+}
+
+enum HullRef {
+    Node {
+        normal: [f32; 3],
+        distance: f32,
+        children: [i32; 2],
+    },
+    Contents(i32),
+}
+
+struct Hull<'l, 'a> {
+    level: &'l Level<'a>,
+    hull: usize,
+    head: i32,
+}
+
+impl<'l, 'a> Hull<'l, 'a> {
+    fn new(level: &'l Level<'a>, model_id: usize, hull: usize) -> ParsingResult<Self> {
+        if hull >= MAX_HULLS {
+            return Err(ParsingError::OutOfRange("bsp hull"));
+        }
+        let model = level
+            .models
+            .get(model_id)
+            .ok_or(ParsingError::OutOfRange("bsp model"))?;
+
+        Ok(Self {
+            level,
+            hull,
+            head: model.nodes[hull].get(),
+        })
+    }
+
+    fn nodes_num(&self) -> usize {
+        if self.hull == 0 {
+            self.level.nodes.len()
+        } else {
+            self.level.clip_nodes.len()
+        }
+    }
+
+    fn resolve(&self, num: i32) -> ParsingResult<HullRef> {
+        let (plane_id, children) = if self.hull == 0 {
+            if num < 0 {
+                let leaf = self
+                    .level
+                    .leaves
+                    .get(!num as usize)
+                    .ok_or(ParsingError::OutOfRange("bsp leaf"))?;
+                return Ok(HullRef::Contents(leaf.contents.get()));
+            }
+            let node = self
+                .level
+                .nodes
+                .get(num as usize)
+                .ok_or(ParsingError::OutOfRange("bsp node"))?;
+            (node.plane_id.get(), node.children)
+        } else {
+            if num < 0 {
+                return Ok(HullRef::Contents(num));
+            }
+            let node = self
+                .level
+                .clip_nodes
+                .get(num as usize)
+                .ok_or(ParsingError::OutOfRange("bsp clip node"))?;
+            (node.plane_id.get(), node.children)
+        };
+
+        let plane = self
+            .level
+            .planes
+            .get(plane_id as usize)
+            .ok_or(ParsingError::OutOfRange("bsp plane"))?;
+
+        Ok(HullRef::Node {
+            normal: vec3(&plane.normal),
+            distance: plane.distance.get(),
+            children: children.map(|child| i32::from(child.get())),
+        })
+    }
+
+    fn point_contents(&self, mut num: i32, point: [f32; 3]) -> ParsingResult<i32> {
+        for _ in 0..=self.level.nodes.len() + self.level.clip_nodes.len() {
+            match self.resolve(num)? {
+                HullRef::Contents(contents) => return Ok(contents),
+                HullRef::Node {
+                    normal,
+                    distance,
+                    children,
+                } => {
+                    let dist = dot(normal, point) - distance;
+                    num = children[usize::from(dist < 0.0)];
+                }
+            }
+        }
+
+        Err(ParsingError::Invalid("bsp hull tree"))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn check_r(
+        &self,
+        num: i32,
+        depth: usize,
+        p1f: f32,
+        p2f: f32,
+        p1: [f32; 3],
+        p2: [f32; 3],
+        trace: &mut Trace,
+    ) -> ParsingResult<bool> {
+        let (normal, distance, children) = match self.resolve(num)? {
+            HullRef::Contents(contents) => {
+                if contents != CONTENTS_SOLID {
+                    trace.all_solid = false;
+                    if contents == CONTENTS_EMPTY {
+                        trace.in_open = true;
+                    } else {
+                        trace.in_water = true;
+                    }
+                } else {
+                    trace.start_solid = true;
+                }
+                return Ok(true);
+            }
+            HullRef::Node {
+                normal,
+                distance,
+                children,
+            } => (normal, distance, children),
+        };
+        // A path longer than the node count can only come from a cycle.
+        if depth >= self.nodes_num() {
+            return Err(ParsingError::Invalid("bsp hull tree"));
+        }
+        let depth = depth + 1;
+
+        let t1 = dot(normal, p1) - distance;
+        let t2 = dot(normal, p2) - distance;
+        if t1 >= 0.0 && t2 >= 0.0 {
+            return self.check_r(children[0], depth, p1f, p2f, p1, p2, trace);
+        }
+        if t1 < 0.0 && t2 < 0.0 {
+            return self.check_r(children[1], depth, p1f, p2f, p1, p2, trace);
+        }
+
+        // Put the crosspoint DIST_EPSILON units on the near side.
+        let mut frac = if t1 < 0.0 {
+            (t1 + DIST_EPSILON) / (t1 - t2)
+        } else {
+            (t1 - DIST_EPSILON) / (t1 - t2)
+        }
+        .clamp(0.0, 1.0);
+        let mut midf = p1f + (p2f - p1f) * frac;
+        let mut mid = lerp(p1, p2, frac);
+        let side = usize::from(t1 < 0.0);
+
+        if !self.check_r(children[side], depth, p1f, midf, p1, mid, trace)? {
+            return Ok(false);
+        }
+        if self.point_contents(children[side ^ 1], mid)? != CONTENTS_SOLID {
+            return self.check_r(children[side ^ 1], depth, midf, p2f, mid, p2, trace);
+        }
+        if trace.all_solid {
+            // Never got out of the solid area.
+            return Ok(false);
+        }
+
+        // The other side of the node is solid, this is the impact point.
+        if side == 0 {
+            trace.plane_normal = normal;
+            trace.plane_distance = distance;
+        } else {
+            trace.plane_normal = normal.map(|v| -v);
+            trace.plane_distance = -distance;
+        }
+
+        while self.point_contents(self.head, mid)? == CONTENTS_SOLID {
+            // Shouldn't really happen, but does occasionally.
+            frac -= 0.1;
+            if frac < 0.0 {
+                trace.fraction = midf;
+                trace.end_pos = mid;
+                return Ok(false);
+            }
+            midf = p1f + (p2f - p1f) * frac;
+            mid = lerp(p1, p2, frac);
+        }
+
+        trace.fraction = midf;
+        trace.end_pos = mid;
+        Ok(false)
+    }
+}
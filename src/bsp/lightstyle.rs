@@ -0,0 +1,181 @@
+use crate::{
+    bsp::{
+        Face, Level,
+        entities::Entity,
+        surface::{has_lightmap, lightmap_extents},
+    },
+    error::{ParsingError, ParsingResult},
+    texture::Rgb,
+};
+
+/// Maximum number of light styles.
+pub const MAX_LIGHTSTYLES: usize = 64;
+/// Maximum number of styles (lightmaps) per face.
+pub const MAX_LIGHTMAPS: usize = 4;
+/// Face style slot value marking an unused lightmap.
+pub const STYLE_NONE: u8 = 255;
+/// First style switchable by entities; lower styles are built-in.
+pub const FIRST_SWITCHABLE_STYLE: usize = 32;
+/// Pattern characters advanced per second.
+pub const LIGHTSTYLE_RATE: f32 = 10.0;
+/// Style value of a fully lit lightmap (`'m'` is slightly brighter).
+pub const LIGHTSTYLE_NORMAL: u32 = 256;
+
+/// Built-in light style patterns (styles 0-11).
+pub const BUILTIN_LIGHTSTYLES: [&[u8]; 12] = [
+    // 0 normal
+    b"m",
+    // 1 flicker (first variety)
+    b"mmnmmommommnonmmonqnmmo",
+    // 2 slow strong pulse
+    b"abcdefghijklmnopqrstuvwxyzyxwvutsrqponmlkjihgfedcba",
+    // 3 candle (first variety)
+    b"mmmmmaaaaammmmmaaaaaabcdefgabcdefg",
+    // 4 fast strobe
+    b"mamamamamama",
+    // 5 gentle pulse
+    b"jklmnopqrstuvwxyzyxwvutsrqponmlkj",
+    // 6 flicker (second variety)
+    b"nmonqnmomnmomomno",
+    // 7 candle (second variety)
+    b"mmmaaaabcdefgmmmmaaaammmaamm",
+    // 8 candle (third variety)
+    b"mmmaaammmaaammmabcdefaaaammmmabcdefmmmaaaa",
+    // 9 slow strobe
+    b"aaaaaaaazzzzzzzz",
+    // 10 fluorescent flicker
+    b"mmamammmmammamamaaamammma",
+    // 11 slow pulse not fading to black
+    b"abcdefghijklmnopqrrqponmlkjihgfedcba",
+];
+
+/// Light entity spawn flag keeping the light off initially.
+const SF_LIGHT_START_OFF: i32 = 1;
+
+/// Table of light style patterns.
+#[derive(Debug, Clone)]
+pub struct LightStyles<'a> {
+    /// Pattern of each style, `'a'` is dark and `'z'` is double bright.
+    pub patterns: [&'a [u8]; MAX_LIGHTSTYLES],
+}
+
+impl Default for LightStyles<'_> {
+    fn default() -> Self {
+        let mut patterns: [&[u8]; MAX_LIGHTSTYLES] = [b""; MAX_LIGHTSTYLES];
+        patterns[..BUILTIN_LIGHTSTYLES.len()].copy_from_slice(&BUILTIN_LIGHTSTYLES);
+        // Style 63 is used by the engine for testing.
+        patterns[63] = b"a";
+        Self { patterns }
+    }
+}
+
+impl<'a> LightStyles<'a> {
+    /// Built-in styles plus the switchable styles defined by light entities.
+    ///
+    /// Mirrors `CLight::Spawn`: a light with `style >= 32` sets its style to
+    /// `"a"` when it starts off, to its `pattern` or to `"m"` otherwise.
+    pub fn from_entities(entities: &[Entity<'a>]) -> Self {
+        let mut styles = Self::default();
+        for entity in entities {
+            let is_light = entity
+                .classname()
+                .is_some_and(|classname| classname.starts_with(b"light"));
+            if !is_light {
+                continue;
+            }
+            let Some(style) = entity
+                .get_i32(b"style")
+                .and_then(|style| usize::try_from(style).ok())
+                .filter(|style| (FIRST_SWITCHABLE_STYLE..MAX_LIGHTSTYLES).contains(style))
+            else {
+                continue;
+            };
+
+            let spawnflags = entity.get_i32(b"spawnflags").unwrap_or(0);
+            styles.patterns[style] = if spawnflags & SF_LIGHT_START_OFF != 0 {
+                b"a"
+            } else {
+                entity.get(b"pattern").unwrap_or(b"m")
+            };
+        }
+
+        styles
+    }
+
+    /// Style value at time `t` (in seconds), `256` being fully lit.
+    pub fn value(&self, style: u8, t: f32) -> u32 {
+        let Some(pattern) = self.patterns.get(usize::from(style)) else {
+            return 0;
+        };
+        if pattern.is_empty() {
+            return LIGHTSTYLE_NORMAL;
+        }
+
+        let frame = (t.max(0.0) * LIGHTSTYLE_RATE) as usize % pattern.len();
+        u32::from(pattern[frame].saturating_sub(b'a').min(b'z' - b'a')) * 22
+    }
+
+    /// Style brightness at time `t` (in seconds), `1.0` being fully lit.
+    pub fn brightness(&self, style: u8, t: f32) -> f32 {
+        self.value(style, t) as f32 / LIGHTSTYLE_NORMAL as f32
+    }
+}
+
+/// Lightmap of a face composited from its styles.
+#[derive(Debug, Clone)]
+pub struct Lightmap {
+    /// Width in samples.
+    pub width: u32,
+    /// Height in samples.
+    pub height: u32,
+    /// Row-major samples.
+    pub samples: Vec<Rgb>,
+}
+
+/// Composites the style lightmaps of a face at time `t` (`R_BuildLightMap`).
+///
+/// Returns `None` for faces without a lightmap.
+pub fn composite_lightmap(
+    level: &Level<'_>,
+    face: &Face,
+    styles: &LightStyles<'_>,
+    t: f32,
+) -> ParsingResult<Option<Lightmap>> {
+    if !has_lightmap(level, face)? {
+        return Ok(None);
+    }
+
+    let extents = lightmap_extents(level, face)?;
+    let samples = extents.samples();
+    let offset = face.lightmap_offset.get() as usize;
+    let mut accum = vec![[0u32; 3]; samples];
+
+    for (map, &style) in face
+        .lighting_styles
+        .iter()
+        .take_while(|&&style| style != STYLE_NONE)
+        .enumerate()
+    {
+        let start = offset + map * samples * 3;
+        let data = level
+            .lighting
+            .get(start..start + samples * 3)
+            .ok_or(ParsingError::OutOfRange("bsp face lightmap"))?;
+        let value = styles.value(style, t);
+
+        for (sum, sample) in accum.iter_mut().zip(data.chunks_exact(3)) {
+            for (channel, &byte) in sum.iter_mut().zip(sample) {
+                *channel += u32::from(byte) * value;
+            }
+        }
+    }
+
+    Ok(Some(Lightmap {
+        width: extents.size[0],
+        height: extents.size[1],
+        samples: accum
+            .into_iter()
+            .map(|sum| sum.map(|channel| (channel >> 8).min(255) as u8))
+            .collect(),
+    }))
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::{
+    bsp::{
+        CONTENTS_CURRENT_0, CONTENTS_CURRENT_DOWN, CONTENTS_LADDER, CONTENTS_LAVA, CONTENTS_SLIME,
+        CONTENTS_WATER, Level,
+        hull::{HULL_HUMAN, HULL_POINT, HULL_SIZES, hull_contents, point_contents, trace},
+    },
+    common::BBox,
+    error::{ParsingError, ParsingResult},
+    math::vec3,
+};
+
+/// Highest step a player climbs without jumping.
+pub const STEP_HEIGHT: f32 = 18.0;
+/// Lowest plane normal Z a player can stand on.
+pub const MIN_WALKABLE_NORMAL_Z: f32 = 0.7;
+
+/// Node flag: feet are in water, slime or lava.
+pub const NAV_WATER: u32 = 1 << 0;
+/// Node flag: node touches a ladder.
+pub const NAV_LADDER: u32 = 1 << 1;
+
+/// Maximum number of stacked floors sampled per column.
+const MAX_FLOORS: usize = 64;
+/// Height tolerance when checking a move lands on the expected node.
+const LANDING_EPSILON: f32 = 2.0;
+
+/// Navigation graph generation settings.
+#[derive(Debug, Clone)]
+pub struct NavOptions {
+    /// Distance between sampled columns.
+    pub cell_size: f32,
+    /// Highest step between walkable nodes.
+    pub step_height: f32,
+    /// Highest drop allowed for one-way links.
+    pub max_drop: f32,
+    /// Lowest floor normal Z considered walkable.
+    pub min_normal_z: f32,
+}
+
+impl Default for NavOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 32.0,
+            step_height: STEP_HEIGHT,
+            max_drop: 200.0,
+            min_normal_z: MIN_WALKABLE_NORMAL_Z,
+        }
+    }
+}
+
+/// Kind of a navigation link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavLinkKind {
+    /// Walkable in both directions (steps included).
+    Walk,
+    /// One-way drop down a ledge.
+    Drop,
+    /// Climb along a ladder.
+    Ladder,
+}
+
+/// Standing spot on a walkable floor.
+#[derive(Debug, Clone)]
+pub struct NavNode {
+    /// Player origin standing on the floor (hull 1 center).
+    pub origin: [f32; 3],
+    /// Floor normal.
+    pub normal: [f32; 3],
+    /// Sampled grid column.
+    pub cell: [i32; 2],
+    /// `NAV_*` flags.
+    pub flags: u32,
+}
+
+/// Directed link between two nodes.
+#[derive(Debug, Clone)]
+pub struct NavLink {
+    /// Source node index.
+    pub from: usize,
+    /// Target node index.
+    pub to: usize,
+    /// Movement needed to follow the link.
+    pub kind: NavLinkKind,
+}
+
+/// Navigation graph of a level.
+#[derive(Debug, Clone, Default)]
+pub struct NavGraph {
+    /// Walkable nodes.
+    pub nodes: Vec<NavNode>,
+    /// Directed links between nodes.
+    pub links: Vec<NavLink>,
+}
+
+impl NavGraph {
+    /// Writes the graph as plain text.
+    ///
+    /// The format is a `NAV 1` line, a `nodes <count>` line followed by
+    /// `x y z nx ny nz flags` lines, then a `links <count>` line followed by
+    /// `from to walk|drop|ladder` lines.
+    pub fn write_text(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "NAV 1")?;
+        writeln!(w, "nodes {}", self.nodes.len())?;
+        for node in &self.nodes {
+            let [x, y, z] = node.origin;
+            let [nx, ny, nz] = node.normal;
+            writeln!(w, "{x} {y} {z} {nx} {ny} {nz} {}", node.flags)?;
+        }
+        writeln!(w, "links {}", self.links.len())?;
+        for link in &self.links {
+            let kind = match link.kind {
+                NavLinkKind::Walk => "walk",
+                NavLinkKind::Drop => "drop",
+                NavLinkKind::Ladder => "ladder",
+            };
+            writeln!(w, "{} {} {kind}", link.from, link.to)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a navigation graph by sampling the standing player hull (hull 1).
+///
+/// Columns are traced down on a grid to find floors flat enough to stand on,
+/// neighbouring floors are linked when a player can walk, step or drop
+/// between them, and nodes touching ladder brushes are linked vertically.
+pub fn build_nav_graph(level: &Level<'_>, options: &NavOptions) -> ParsingResult<NavGraph> {
+    if options.cell_size <= 0.0 {
+        return Err(ParsingError::Invalid("nav cell size"));
+    }
+    let world = level
+        .models
+        .first()
+        .ok_or(ParsingError::OutOfRange("bsp world model"))?;
+    let min = vec3(&world.bounds.min);
+    let max = vec3(&world.bounds.max);
+    let volumes = special_volumes(level)?;
+
+    let mut graph = NavGraph::default();
+    let mut cells: HashMap<[i32; 2], Vec<usize>> = HashMap::new();
+
+    let columns = [0, 1].map(|axis| ((max[axis] - min[axis]) / options.cell_size).ceil() as i32);
+    for ix in 0..columns[0] {
+        for iy in 0..columns[1] {
+            let x = min[0] + (ix as f32 + 0.5) * options.cell_size;
+            let y = min[1] + (iy as f32 + 0.5) * options.cell_size;
+            let bottom = min[2] + HULL_SIZES[HULL_HUMAN].min[2];
+            let mut z = max[2] + HULL_SIZES[HULL_HUMAN].max[2];
+
+            for _ in 0..MAX_FLOORS {
+                let floor = trace(level, 0, HULL_HUMAN, [x, y, z], [x, y, bottom])?;
+                if floor.all_solid || floor.fraction >= 1.0 {
+                    break;
+                }
+
+                if floor.plane_normal[2] >= options.min_normal_z {
+                    let origin = floor.end_pos;
+                    let flags = node_flags(level, &volumes, origin)?;
+                    cells.entry([ix, iy]).or_default().push(graph.nodes.len());
+                    graph.nodes.push(NavNode {
+                        origin,
+                        normal: floor.plane_normal,
+                        cell: [ix, iy],
+                        flags,
+                    });
+                }
+
+                // Continue below the floor, the next trace starts in solid and
+                // stops at the next floor under it.
+                z = floor.end_pos[2] - 1.0;
+                if z <= bottom {
+                    break;
+                }
+            }
+        }
+    }
+
+    for (from, node) in graph.nodes.iter().enumerate() {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let Some(targets) = cells.get(&[node.cell[0] + dx, node.cell[1] + dy]) else {
+                    continue;
+                };
+                for &to in targets {
+                    if let Some(kind) = link_kind(level, options, node, &graph.nodes[to])? {
+                        graph.links.push(NavLink { from, to, kind });
+                    }
+                }
+            }
+        }
+    }
+
+    link_ladders(&mut graph, &volumes, options);
+
+    Ok(graph)
+}
+
+struct SpecialVolume {
+    model_id: usize,
+    bounds: BBox<[f32; 3]>,
+    water: bool,
+    ladder: bool,
+}
+
+// Brush models holding water or ladder contents (`func_water`, `func_ladder`).
+fn special_volumes(level: &Level<'_>) -> ParsingResult<Vec<SpecialVolume>> {
+    let mut volumes = Vec::new();
+    for (model_id, model) in level.models.iter().enumerate().skip(1) {
+        let mut water = false;
+        let mut ladder = false;
+        hull_contents(level, model_id, HULL_POINT, |contents| {
+            water |= is_liquid(contents);
+            ladder |= contents == CONTENTS_LADDER;
+        })?;
+
+        if water || ladder {
+            volumes.push(SpecialVolume {
+                model_id,
+                bounds: BBox {
+                    min: vec3(&model.bounds.min),
+                    max: vec3(&model.bounds.max),
+                },
+                water,
+                ladder,
+            });
+        }
+    }
+
+    Ok(volumes)
+}
+
+fn node_flags(
+    level: &Level<'_>,
+    volumes: &[SpecialVolume],
+    origin: [f32; 3],
+) -> ParsingResult<u32> {
+    let hull = &HULL_SIZES[HULL_HUMAN];
+    let feet = [origin[0], origin[1], origin[2] + hull.min[2] + 1.0];
+    let reach = BBox {
+        min: [0, 1, 2].map(|axis| origin[axis] + hull.min[axis]),
+        max: [0, 1, 2].map(|axis| origin[axis] + hull.max[axis]),
+    };
+
+    let mut flags = 0;
+    if is_liquid(point_contents(level, 0, HULL_POINT, feet)?) {
+        flags |= NAV_WATER;
+    }
+    for volume in volumes {
+        if !overlaps(&volume.bounds, &reach) {
+            continue;
+        }
+        if volume.ladder {
+            flags |= NAV_LADDER;
+        }
+        if volume.water && is_liquid(point_contents(level, volume.model_id, HULL_POINT, feet)?) {
+            flags |= NAV_WATER;
+        }
+    }
+
+    Ok(flags)
+}
+
+fn link_kind(
+    level: &Level<'_>,
+    options: &NavOptions,
+    from: &NavNode,
+    to: &NavNode,
+) -> ParsingResult<Option<NavLinkKind>> {
+    let dz = to.origin[2] - from.origin[2];
+    let kind = if dz > options.step_height {
+        return Ok(None);
+    } else if dz >= -options.step_height {
+        NavLinkKind::Walk
+    } else if dz >= -options.max_drop {
+        NavLinkKind::Drop
+    } else {
+        return Ok(None);
+    };
+
+    // Move at step height, then settle down onto the target floor.
+    let raised = from.origin[2] + options.step_height;
+    let start = [from.origin[0], from.origin[1], raised];
+    let above = [to.origin[0], to.origin[1], raised];
+    if trace(level, 0, HULL_HUMAN, from.origin, start)?.fraction < 1.0 {
+        return Ok(None);
+    }
+    let across = trace(level, 0, HULL_HUMAN, start, above)?;
+    if across.start_solid || across.fraction < 1.0 {
+        return Ok(None);
+    }
+    let below = [to.origin[0], to.origin[1], to.origin[2] - LANDING_EPSILON];
+    let down = trace(level, 0, HULL_HUMAN, above, below)?;
+    if down.start_solid || (down.end_pos[2] - to.origin[2]).abs() > LANDING_EPSILON {
+        return Ok(None);
+    }
+
+    Ok(Some(kind))
+}
+
+fn link_ladders(graph: &mut NavGraph, volumes: &[SpecialVolume], options: &NavOptions) {
+    let hull = &HULL_SIZES[HULL_HUMAN];
+    let slack = options.cell_size * 0.5;
+    for volume in volumes.iter().filter(|volume| volume.ladder) {
+        let reach = BBox {
+            min: [
+                volume.bounds.min[0] - hull.max[0] - slack,
+                volume.bounds.min[1] - hull.max[1] - slack,
+                volume.bounds.min[2] - hull.max[2] - options.step_height,
+            ],
+            max: [
+                volume.bounds.max[0] - hull.min[0] + slack,
+                volume.bounds.max[1] - hull.min[1] + slack,
+                volume.bounds.max[2] - hull.min[2] + options.step_height,
+            ],
+        };
+
+        let near = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| {
+                overlaps(
+                    &reach,
+                    &BBox {
+                        min: node.origin,
+                        max: node.origin,
+                    },
+                )
+            })
+            .map(|(node_id, node)| (node_id, node.origin[2]));
+        let mut bottom: Option<(usize, f32)> = None;
+        let mut top: Option<(usize, f32)> = None;
+        for (node_id, z) in near {
+            if bottom.is_none_or(|(_, bottom_z)| z < bottom_z) {
+                bottom = Some((node_id, z));
+            }
+            if top.is_none_or(|(_, top_z)| z > top_z) {
+                top = Some((node_id, z));
+            }
+        }
+
+        if let (Some((bottom, bottom_z)), Some((top, top_z))) = (bottom, top)
+            && top_z - bottom_z > options.step_height
+        {
+            graph.nodes[bottom].flags |= NAV_LADDER;
+            graph.nodes[top].flags |= NAV_LADDER;
+            for (from, to) in [(bottom, top), (top, bottom)] {
+                graph.links.push(NavLink {
+                    from,
+                    to,
+                    kind: NavLinkKind::Ladder,
+                });
+            }
+        }
+    }
+}
+
+fn is_liquid(contents: i32) -> bool {
+    matches!(contents, CONTENTS_WATER | CONTENTS_SLIME | CONTENTS_LAVA)
+        || (CONTENTS_CURRENT_DOWN..=CONTENTS_CURRENT_0).contains(&contents)
+}
+
+fn overlaps(a: &BBox<[f32; 3]>, b: &BBox<[f32; 3]>) -> bool {
+    (0..3).all(|axis| a.min[axis] <= b.max[axis] && a.max[axis] >= b.min[axis])
+}
@@ -0,0 +1,230 @@
+use crate::{
+    bsp::{CONTENTS_SOLID, Leaf, Level, NodeChild, Plane},
+    common::BBox,
+    error::{ParsingError, ParsingResult},
+    math::vec3s,
+};
+
+/// Side of a plane a volume lies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaneSide {
+    /// Entirely in front of the plane.
+    Front,
+    /// Entirely behind the plane.
+    Back,
+    /// Crossing the plane.
+    Both,
+}
+
+/// Classifies a box against a plane (`BoxOnPlaneSide`).
+pub fn box_on_plane_side(bounds: &BBox<[f32; 3]>, plane: &Plane) -> PlaneSide {
+    let mut near = [0.0; 3];
+    let mut far = [0.0; 3];
+    for axis in 0..3 {
+        if plane.normal[axis].get() >= 0.0 {
+            near[axis] = bounds.min[axis];
+            far[axis] = bounds.max[axis];
+        } else {
+            near[axis] = bounds.max[axis];
+            far[axis] = bounds.min[axis];
+        }
+    }
+
+    let front = plane.distance_to(far) >= 0.0;
+    let back = plane.distance_to(near) < 0.0;
+    match (front, back) {
+        (true, false) => PlaneSide::Front,
+        (false, true) => PlaneSide::Back,
+        _ => PlaneSide::Both,
+    }
+}
+
+/// Classifies a sphere against a plane.
+pub fn sphere_on_plane_side(center: [f32; 3], radius: f32, plane: &Plane) -> PlaneSide {
+    let dist = plane.distance_to(center);
+    if dist > radius {
+        PlaneSide::Front
+    } else if dist < -radius {
+        PlaneSide::Back
+    } else {
+        PlaneSide::Both
+    }
+}
+
+/// Calls `f` with the index of every non-solid world leaf touching the box.
+pub fn leaves_in_box(
+    level: &Level<'_>,
+    bounds: &BBox<[f32; 3]>,
+    mut f: impl FnMut(usize),
+) -> ParsingResult<()> {
+    walk_leaves(
+        level,
+        &mut |plane| box_on_plane_side(bounds, plane),
+        &mut |leaf| leaf_touches_box(leaf, bounds),
+        &mut f,
+    )
+}
+
+/// Calls `f` with the index of every non-solid world leaf touching the sphere.
+pub fn leaves_in_sphere(
+    level: &Level<'_>,
+    center: [f32; 3],
+    radius: f32,
+    mut f: impl FnMut(usize),
+) -> ParsingResult<()> {
+    walk_leaves(
+        level,
+        &mut |plane| sphere_on_plane_side(center, radius, plane),
+        &mut |leaf| leaf_touches_sphere(leaf, center, radius),
+        &mut f,
+    )
+}
+
+/// Calls `f` with the index of every face marked in the leaves touching the box.
+///
+/// Faces shared by several leaves are reported once per leaf.
+pub fn faces_in_box(
+    level: &Level<'_>,
+    bounds: &BBox<[f32; 3]>,
+    mut f: impl FnMut(usize),
+) -> ParsingResult<()> {
+    let mut result = Ok(());
+    leaves_in_box(level, bounds, |leaf_id| {
+        if result.is_ok() {
+            result = leaf_faces(level, leaf_id, &mut f);
+        }
+    })?;
+    result
+}
+
+/// Calls `f` with the index of every face marked in the leaves touching the sphere.
+///
+/// Faces shared by several leaves are reported once per leaf.
+pub fn faces_in_sphere(
+    level: &Level<'_>,
+    center: [f32; 3],
+    radius: f32,
+    mut f: impl FnMut(usize),
+) -> ParsingResult<()> {
+    let mut result = Ok(());
+    leaves_in_sphere(level, center, radius, |leaf_id| {
+        if result.is_ok() {
+            result = leaf_faces(level, leaf_id, &mut f);
+        }
+    })?;
+    result
+}
+
+/// Calls `f` with the index of every face marked in the leaf.
+pub fn leaf_faces(
+    level: &Level<'_>,
+    leaf_id: usize,
+    mut f: impl FnMut(usize),
+) -> ParsingResult<()> {
+    let leaf = level
+        .leaves
+        .get(leaf_id)
+        .ok_or(ParsingError::OutOfRange("bsp leaf"))?;
+    let first = usize::from(leaf.first_mark_surface_id.get());
+    let count = usize::from(leaf.mark_surfaces_num.get());
+    let marks = level
+        .mark_surfaces
+        .get(first..first + count)
+        .ok_or(ParsingError::OutOfRange("bsp mark surfaces"))?;
+
+    for mark in marks {
+        f(usize::from(mark.get()));
+    }
+
+    Ok(())
+}
+
+/// Head node of the world model.
+pub(crate) fn world_head_node(level: &Level<'_>) -> Option<NodeChild> {
+    let model = level.models.first()?;
+    usize::try_from(model.nodes[0].get())
+        .ok()
+        .map(NodeChild::Node)
+}
+
+fn walk_leaves(
+    level: &Level<'_>,
+    classify: &mut dyn FnMut(&Plane) -> PlaneSide,
+    touches: &mut dyn FnMut(&Leaf) -> bool,
+    f: &mut dyn FnMut(usize),
+) -> ParsingResult<()> {
+    match world_head_node(level) {
+        Some(head) => walk_leaves_r(level, head, &mut 0, classify, touches, f),
+        None => Ok(()),
+    }
+}
+
+fn walk_leaves_r(
+    level: &Level<'_>,
+    child: NodeChild,
+    visited: &mut usize,
+    classify: &mut dyn FnMut(&Plane) -> PlaneSide,
+    touches: &mut dyn FnMut(&Leaf) -> bool,
+    f: &mut dyn FnMut(usize),
+) -> ParsingResult<()> {
+    // A tree of `n` nodes has `2n + 1` references, more means shared children
+    // or a cycle.
+    *visited += 1;
+    if *visited > 2 * level.nodes.len() + 1 {
+        return Err(ParsingError::Invalid("bsp node tree"));
+    }
+
+    match child {
+        NodeChild::Leaf(leaf_id) => {
+            let leaf = level
+                .leaves
+                .get(leaf_id)
+                .ok_or(ParsingError::OutOfRange("bsp leaf"))?;
+            if leaf.contents.get() != CONTENTS_SOLID && touches(leaf) {
+                f(leaf_id);
+            }
+            Ok(())
+        }
+        NodeChild::Node(node_id) => {
+            let node = level
+                .nodes
+                .get(node_id)
+                .ok_or(ParsingError::OutOfRange("bsp node"))?;
+            let plane = level
+                .planes
+                .get(node.plane_id.get() as usize)
+                .ok_or(ParsingError::OutOfRange("bsp plane"))?;
+
+            match classify(plane) {
+                PlaneSide::Front => {
+                    walk_leaves_r(level, node.child(0), visited, classify, touches, f)
+                }
+                PlaneSide::Back => {
+                    walk_leaves_r(level, node.child(1), visited, classify, touches, f)
+                }
+                PlaneSide::Both => {
+                    walk_leaves_r(level, node.child(0), visited, classify, touches, f)?;
+                    walk_leaves_r(level, node.child(1), visited, classify, touches, f)
+                }
+            }
+        }
+    }
+}
+
+fn leaf_touches_box(leaf: &Leaf, bounds: &BBox<[f32; 3]>) -> bool {
+    let min = vec3s(&leaf.bounds.min);
+    let max = vec3s(&leaf.bounds.max);
+    (0..3).all(|axis| bounds.min[axis] <= max[axis] && bounds.max[axis] >= min[axis])
+}
+
+fn leaf_touches_sphere(leaf: &Leaf, center: [f32; 3], radius: f32) -> bool {
+    let min = vec3s(&leaf.bounds.min);
+    let max = vec3s(&leaf.bounds.max);
+    let dist_sq: f32 = (0..3)
+        .map(|axis| {
+            let clamped = center[axis].max(min[axis]).min(max[axis]);
+            (center[axis] - clamped).powi(2)
+        })
+        .sum();
+    dist_sq <= radius * radius
+}
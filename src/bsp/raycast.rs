@@ -0,0 +1,187 @@
+use crate::{
+    bsp::{
+        Face, Level, NodeChild,
+        query::world_head_node,
+        surface::{
+            face_plane, face_texture, face_texture_info, face_vertices, has_lightmap,
+            lightmap_extents, texture_coords,
+        },
+    },
+    common::cstring_bytes,
+    error::{ParsingError, ParsingResult},
+    math::{cross, dot, lerp, sub},
+};
+
+/// Tolerance used for point-in-polygon tests.
+const ON_EDGE_EPSILON: f32 = 0.01;
+
+/// Surface hit by a ray.
+#[derive(Debug, Clone)]
+pub struct RayHit<'a> {
+    /// Index of the hit face.
+    pub face_id: usize,
+    /// Name of the face texture (C string, not guaranteed UTF-8).
+    pub texture_name: &'a [u8],
+    /// Hit position in world space.
+    pub position: [f32; 3],
+    /// Normal of the hit face.
+    pub normal: [f32; 3],
+    /// Fraction of the ray travelled before the hit.
+    pub fraction: f32,
+    /// Texture coordinates (in texels) at the hit.
+    pub tex_coords: [f32; 2],
+    /// Lightmap sample coordinates at the hit, if the face has a lightmap.
+    pub lightmap_coords: Option<[f32; 2]>,
+}
+
+/// Casts a ray from `start` to `end` against the world render geometry (hull 0).
+///
+/// Faces are tested exactly against their polygons, and the closest hit is
+/// returned. Faces are hit from both sides.
+pub fn raycast<'a>(
+    level: &Level<'a>,
+    start: [f32; 3],
+    end: [f32; 3],
+) -> ParsingResult<Option<RayHit<'a>>> {
+    let Some(head) = world_head_node(level) else {
+        return Ok(None);
+    };
+    let Some((face_id, position)) = cast_r(level, head, 0, start, end, sub(end, start))? else {
+        return Ok(None);
+    };
+
+    let face = &level.faces[face_id];
+    let (normal, _) = face_plane(level, face)?;
+    let texture_info = face_texture_info(level, face)?;
+    let texture = face_texture(level, face)?;
+    let tex_coords = texture_coords(texture_info, position);
+    let lightmap_coords = if has_lightmap(level, face)? {
+        Some(lightmap_extents(level, face)?.lightmap_coords(tex_coords))
+    } else {
+        None
+    };
+
+    let dir = sub(end, start);
+    let length_sq = dot(dir, dir);
+    let fraction = if length_sq > 0.0 {
+        dot(sub(position, start), dir) / length_sq
+    } else {
+        0.0
+    };
+
+    Ok(Some(RayHit {
+        face_id,
+        texture_name: cstring_bytes(&texture.header.name),
+        position,
+        normal,
+        fraction,
+        tex_coords,
+        lightmap_coords,
+    }))
+}
+
+fn cast_r(
+    level: &Level<'_>,
+    child: NodeChild,
+    depth: usize,
+    start: [f32; 3],
+    end: [f32; 3],
+    dir: [f32; 3],
+) -> ParsingResult<Option<(usize, [f32; 3])>> {
+    let NodeChild::Node(node_id) = child else {
+        return Ok(None);
+    };
+    if depth >= level.nodes.len() {
+        return Err(ParsingError::Invalid("bsp node tree"));
+    }
+    let node = level
+        .nodes
+        .get(node_id)
+        .ok_or(ParsingError::OutOfRange("bsp node"))?;
+    let plane = level
+        .planes
+        .get(node.plane_id.get() as usize)
+        .ok_or(ParsingError::OutOfRange("bsp plane"))?;
+
+    let front = plane.distance_to(start);
+    let back = plane.distance_to(end);
+    let side = usize::from(front < 0.0);
+    let depth = depth + 1;
+
+    if (back < 0.0) == (front < 0.0) {
+        return cast_r(level, node.child(side), depth, start, end, dir);
+    }
+
+    let mid = lerp(start, end, front / (front - back));
+    if let Some(hit) = cast_r(level, node.child(side), depth, start, mid, dir)? {
+        return Ok(Some(hit));
+    }
+
+    let first = usize::from(node.first_face_id.get());
+    let count = usize::from(node.faces_num.get());
+    let faces = level
+        .faces
+        .get(first..first + count)
+        .ok_or(ParsingError::OutOfRange("bsp node faces"))?;
+
+    // Prefer the face looking at the ray if both sides of the plane have one.
+    let mut backface = None;
+    for (face_id, face) in (first..).zip(faces) {
+        let (normal, _) = face_plane(level, face)?;
+        if !contains_point(level, face, normal, mid)? {
+            continue;
+        }
+        if dot(normal, dir) <= 0.0 {
+            return Ok(Some((face_id, mid)));
+        }
+        backface.get_or_insert(face_id);
+    }
+    if let Some(face_id) = backface {
+        return Ok(Some((face_id, mid)));
+    }
+
+    cast_r(level, node.child(side ^ 1), depth, mid, end, dir)
+}
+
+fn contains_point(
+    level: &Level<'_>,
+    face: &Face,
+    normal: [f32; 3],
+    point: [f32; 3],
+) -> ParsingResult<bool> {
+    let mut first = None;
+    let mut prev = None;
+    let mut positive = false;
+    let mut negative = false;
+
+    let mut check_edge = |from: [f32; 3], to: [f32; 3]| {
+        let edge = sub(to, from);
+        let side = dot(cross(edge, sub(point, from)), normal);
+        let length_sq = dot(edge, edge);
+        if side * side <= ON_EDGE_EPSILON * ON_EDGE_EPSILON * length_sq {
+            return;
+        }
+        if side > 0.0 {
+            positive = true;
+        } else {
+            negative = true;
+        }
+    };
+
+    face_vertices(level, face, |vertex| {
+        if let Some(prev) = prev {
+            check_edge(prev, vertex);
+        } else {
+            first = Some(vertex);
+        }
+        prev = Some(vertex);
+    })?;
+
+    match (first, prev) {
+        (Some(first), Some(last)) => {
+            check_edge(last, first);
+            Ok(!(positive && negative))
+        }
+        _ => Ok(false),
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    bsp::{
+        Level, NodeChild, Plane,
+        query::{PlaneSide, box_on_plane_side, world_head_node},
+        vis::{leaf_pvs, point_leaf, vis_leaves_num},
+    },
+    common::{BBox, Vec3s},
+    error::{ParsingError, ParsingResult},
+    math::vec3s,
+};
+
+/// Visible surface walker over the world model (`R_RecursiveWorldNode`).
+///
+/// Keeps per-node, per-leaf and per-face frame marks between calls, so a
+/// walker should be created once per level and reused every frame.
+pub struct SurfaceWalker {
+    node_parents: Vec<Option<usize>>,
+    leaf_parents: Vec<Option<usize>>,
+    node_frames: Vec<u32>,
+    leaf_frames: Vec<u32>,
+    face_frames: Vec<u32>,
+    frame: u32,
+}
+
+impl SurfaceWalker {
+    /// Creates a walker for the level's world tree.
+    pub fn new(level: &Level<'_>) -> ParsingResult<Self> {
+        let mut node_parents = vec![None; level.nodes.len()];
+        let mut leaf_parents = vec![None; level.leaves.len()];
+
+        let mut stack = Vec::new();
+        if let Some(NodeChild::Node(head)) = world_head_node(level) {
+            stack.push(head);
+        }
+        while let Some(node_id) = stack.pop() {
+            let node = level
+                .nodes
+                .get(node_id)
+                .ok_or(ParsingError::OutOfRange("bsp node"))?;
+            for side in 0..2 {
+                match node.child(side) {
+                    NodeChild::Node(child) => {
+                        let parent = node_parents
+                            .get_mut(child)
+                            .ok_or(ParsingError::OutOfRange("bsp node"))?;
+                        if parent.is_some() {
+                            return Err(ParsingError::Invalid("bsp node tree"));
+                        }
+                        *parent = Some(node_id);
+                        stack.push(child);
+                    }
+                    NodeChild::Leaf(child) => {
+                        *leaf_parents
+                            .get_mut(child)
+                            .ok_or(ParsingError::OutOfRange("bsp leaf"))? = Some(node_id);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            node_frames: vec![0; node_parents.len()],
+            leaf_frames: vec![0; leaf_parents.len()],
+            face_frames: vec![0; level.faces.len()],
+            node_parents,
+            leaf_parents,
+            frame: 0,
+        })
+    }
+
+    /// Calls `f` with every visible world face in front-to-back order.
+    ///
+    /// Faces are visible when they belong to a leaf in the PVS of the camera
+    /// leaf, face the camera and are inside the frustum. Frustum planes must
+    /// have their normals pointing inside the frustum.
+    pub fn walk(
+        &mut self,
+        level: &Level<'_>,
+        origin: [f32; 3],
+        frustum: &[Plane],
+        mut f: impl FnMut(usize),
+    ) -> ParsingResult<()> {
+        let Some(head) = world_head_node(level) else {
+            return Ok(());
+        };
+
+        self.frame = self.frame.wrapping_add(1);
+        if self.frame == 0 {
+            self.node_frames.fill(0);
+            self.leaf_frames.fill(0);
+            self.face_frames.fill(0);
+            self.frame = 1;
+        }
+
+        self.mark_leaves(level, origin)?;
+        self.walk_r(level, head, origin, frustum, &mut f)
+    }
+
+    fn mark_leaves(&mut self, level: &Level<'_>, origin: [f32; 3]) -> ParsingResult<()> {
+        let pvs = leaf_pvs(level, point_leaf(level, origin)?)?;
+        let leaves_num = vis_leaves_num(level).min(self.leaf_frames.len().saturating_sub(1));
+
+        for leaf_id in 1..=leaves_num {
+            if !pvs.is_visible(leaf_id) {
+                continue;
+            }
+
+            self.leaf_frames[leaf_id] = self.frame;
+            let mut parent = self.leaf_parents[leaf_id];
+            while let Some(node_id) = parent {
+                if self.node_frames[node_id] == self.frame {
+                    break;
+                }
+                self.node_frames[node_id] = self.frame;
+                parent = self.node_parents[node_id];
+            }
+        }
+
+        Ok(())
+    }
+
+    fn walk_r(
+        &mut self,
+        level: &Level<'_>,
+        child: NodeChild,
+        origin: [f32; 3],
+        frustum: &[Plane],
+        f: &mut dyn FnMut(usize),
+    ) -> ParsingResult<()> {
+        match child {
+            NodeChild::Leaf(leaf_id) => {
+                if self.leaf_frames.get(leaf_id) != Some(&self.frame) {
+                    return Ok(());
+                }
+                let leaf = level
+                    .leaves
+                    .get(leaf_id)
+                    .ok_or(ParsingError::OutOfRange("bsp leaf"))?;
+                if is_culled(&leaf.bounds, frustum) {
+                    return Ok(());
+                }
+
+                let first = usize::from(leaf.first_mark_surface_id.get());
+                let count = usize::from(leaf.mark_surfaces_num.get());
+                let marks = level
+                    .mark_surfaces
+                    .get(first..first + count)
+                    .ok_or(ParsingError::OutOfRange("bsp mark surfaces"))?;
+                for mark in marks {
+                    let frame = self
+                        .face_frames
+                        .get_mut(usize::from(mark.get()))
+                        .ok_or(ParsingError::OutOfRange("bsp face"))?;
+                    *frame = self.frame;
+                }
+
+                Ok(())
+            }
+            NodeChild::Node(node_id) => {
+                if self.node_frames.get(node_id) != Some(&self.frame) {
+                    return Ok(());
+                }
+                let node = level
+                    .nodes
+                    .get(node_id)
+                    .ok_or(ParsingError::OutOfRange("bsp node"))?;
+                if is_culled(&node.bounds, frustum) {
+                    return Ok(());
+                }
+                let plane = level
+                    .planes
+                    .get(node.plane_id.get() as usize)
+                    .ok_or(ParsingError::OutOfRange("bsp plane"))?;
+                let side = usize::from(plane.distance_to(origin) < 0.0);
+
+                self.walk_r(level, node.child(side), origin, frustum, f)?;
+
+                let first = usize::from(node.first_face_id.get());
+                let count = usize::from(node.faces_num.get());
+                let faces = level
+                    .faces
+                    .get(first..first + count)
+                    .ok_or(ParsingError::OutOfRange("bsp node faces"))?;
+                for (face_id, face) in (first..).zip(faces) {
+                    let facing = usize::from(face.plane_side.get() != 0) == side;
+                    let frame = self
+                        .face_frames
+                        .get(face_id)
+                        .ok_or(ParsingError::OutOfRange("bsp face"))?;
+                    if facing && *frame == self.frame {
+                        f(face_id);
+                    }
+                }
+
+                self.walk_r(level, node.child(side ^ 1), origin, frustum, f)
+            }
+        }
+    }
+}
+
+fn is_culled(bounds: &BBox<Vec3s>, frustum: &[Plane]) -> bool {
+    let bounds = BBox {
+        min: vec3s(&bounds.min),
+        max: vec3s(&bounds.max),
+    };
+    frustum
+        .iter()
+        .any(|plane| box_on_plane_side(&bounds, plane) == PlaneSide::Back)
+}
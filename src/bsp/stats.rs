@@ -0,0 +1,183 @@
+use std::mem::size_of;
+
+use crate::{
+    bsp::{
+        BSP_LUMPS, LUMP_CLIPNODES, LUMP_EDGES, LUMP_ENTITIES, LUMP_FACES, LUMP_LEAVES,
+        LUMP_LIGHTING, LUMP_MARKSURFACES, LUMP_MODELS, LUMP_NODES, LUMP_PLANES, LUMP_SURFEDGES,
+        LUMP_TEXINFO, LUMP_TEXTURES, LUMP_VERTICES, LUMP_VISIBILITY, Level, LevelHeader,
+        entities::entities,
+        lightstyle::STYLE_NONE,
+        surface::{has_lightmap, lightmap_extents},
+        vis::vis_leaves_num,
+    },
+    error::ParsingResult,
+    texture::{MIP_LEVELS, Rgb},
+    util::mip_level_size,
+};
+
+/// Lump names indexed like `LevelHeader::lumps`, as printed by `bspinfo`.
+pub const LUMP_NAMES: [&str; BSP_LUMPS] = [
+    "entities",
+    "planes",
+    "textures",
+    "vertexes",
+    "visibility",
+    "nodes",
+    "texinfo",
+    "faces",
+    "lighting",
+    "clipnodes",
+    "leaves",
+    "marksurfaces",
+    "edges",
+    "surfedges",
+    "models",
+];
+
+/// Size of a lump.
+#[derive(Debug, Clone)]
+pub struct LumpStats {
+    /// Lump name.
+    pub name: &'static str,
+    /// Size in bytes.
+    pub size: usize,
+    /// Number of elements (entities for the entity lump, bytes for visibility
+    /// and lighting).
+    pub count: usize,
+    /// Share of the file size, `0.0..=100.0`.
+    pub percentage: f32,
+}
+
+/// Size and content breakdown of a level (`bspinfo`/`-chart` like).
+#[derive(Debug, Clone)]
+pub struct LevelStats {
+    /// File size (header and lumps).
+    pub file_size: usize,
+    /// Lumps indexed like `LevelHeader::lumps`.
+    pub lumps: Vec<LumpStats>,
+    /// Number of textures stored in the BSP.
+    pub embedded_textures: usize,
+    /// Number of textures loaded from WADs.
+    pub external_textures: usize,
+    /// Memory of embedded textures (mip levels and palette).
+    pub embedded_texture_bytes: usize,
+    /// Memory of textures loaded from WADs (mip levels and palette).
+    pub external_texture_bytes: usize,
+    /// Number of faces with a lightmap.
+    pub lit_faces: usize,
+    /// Lightmap memory needed by faces, every style included.
+    pub lightmap_bytes: usize,
+    /// Average number of vertices per face.
+    pub average_face_vertices: f32,
+    /// Number of leaves covered by visibility data.
+    pub vis_clusters: usize,
+    /// Size of the visibility data once decompressed.
+    pub visdata_uncompressed: usize,
+    /// Uncompressed to compressed visibility size ratio, `0.0` without visdata.
+    pub vis_compression_ratio: f32,
+}
+
+/// Computes size statistics of a level.
+pub fn stats(level: &Level<'_>) -> ParsingResult<LevelStats> {
+    let header = level.header;
+    let file_size = header
+        .lumps
+        .iter()
+        .map(|lump| lump.offset.get() as usize + lump.size.get() as usize)
+        .fold(size_of::<LevelHeader>(), usize::max);
+
+    let mut counts = [0; BSP_LUMPS];
+    counts[LUMP_ENTITIES] = entities(level.entities)?.len();
+    counts[LUMP_PLANES] = level.planes.len();
+    counts[LUMP_TEXTURES] = level.textures.len();
+    counts[LUMP_VERTICES] = level.vertices.len();
+    counts[LUMP_VISIBILITY] = level.visdata.len();
+    counts[LUMP_NODES] = level.nodes.len();
+    counts[LUMP_TEXINFO] = level.texture_infos.len();
+    counts[LUMP_FACES] = level.faces.len();
+    counts[LUMP_LIGHTING] = level.lighting.len();
+    counts[LUMP_CLIPNODES] = level.clip_nodes.len();
+    counts[LUMP_LEAVES] = level.leaves.len();
+    counts[LUMP_MARKSURFACES] = level.mark_surfaces.len();
+    counts[LUMP_EDGES] = level.edges.len();
+    counts[LUMP_SURFEDGES] = level.surfedges.len();
+    counts[LUMP_MODELS] = level.models.len();
+
+    let lumps = header
+        .lumps
+        .iter()
+        .zip(LUMP_NAMES)
+        .zip(counts)
+        .map(|((lump, name), count)| {
+            let size = lump.size.get() as usize;
+            LumpStats {
+                name,
+                size,
+                count,
+                percentage: size as f32 * 100.0 / file_size as f32,
+            }
+        })
+        .collect();
+
+    let mut stats = LevelStats {
+        file_size,
+        lumps,
+        embedded_textures: 0,
+        external_textures: 0,
+        embedded_texture_bytes: 0,
+        external_texture_bytes: 0,
+        lit_faces: 0,
+        lightmap_bytes: 0,
+        average_face_vertices: 0.0,
+        vis_clusters: 0,
+        visdata_uncompressed: 0,
+        vis_compression_ratio: 0.0,
+    };
+
+    for texture in &level.textures {
+        let mut size = size_of::<u16>() + 256 * size_of::<Rgb>();
+        for mip in 0..MIP_LEVELS {
+            size += mip_level_size(
+                texture.header.width.get(),
+                texture.header.height.get(),
+                mip,
+                "bsp texture",
+            )?;
+        }
+
+        if texture.data.is_some() {
+            stats.embedded_textures += 1;
+            stats.embedded_texture_bytes += size;
+        } else {
+            stats.external_textures += 1;
+            stats.external_texture_bytes += size;
+        }
+    }
+
+    let mut vertices_num = 0;
+    for face in level.faces {
+        vertices_num += usize::from(face.surfedges_num.get());
+        if has_lightmap(level, face)? {
+            let styles = face
+                .lighting_styles
+                .iter()
+                .take_while(|&&style| style != STYLE_NONE)
+                .count();
+            stats.lit_faces += 1;
+            stats.lightmap_bytes +=
+                lightmap_extents(level, face)?.samples() * styles * size_of::<Rgb>();
+        }
+    }
+    if !level.faces.is_empty() {
+        stats.average_face_vertices = vertices_num as f32 / level.faces.len() as f32;
+    }
+
+    stats.vis_clusters = vis_leaves_num(level);
+    stats.visdata_uncompressed = stats.vis_clusters * stats.vis_clusters.div_ceil(8);
+    if !level.visdata.is_empty() {
+        stats.vis_compression_ratio =
+            stats.visdata_uncompressed as f32 / level.visdata.len() as f32;
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,163 @@
+use crate::{
+    bsp::{Face, Level, TEXTURE_SPECIAL, TextureInfo},
+    error::{ParsingError, ParsingResult},
+    math::{dot, vec3},
+    texture::MipTexture,
+};
+
+/// Size of a lightmap sample in texels.
+pub const LIGHTMAP_SCALE: f32 = 16.0;
+
+/// Lightmap placement of a face in texture space (`CalcSurfaceExtents`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightmapExtents {
+    /// Texture coordinates of the first lightmap sample.
+    pub mins: [i32; 2],
+    /// Lightmap size in samples.
+    pub size: [u32; 2],
+}
+
+impl LightmapExtents {
+    /// Number of samples in a single style of the lightmap.
+    pub fn samples(&self) -> usize {
+        self.size[0] as usize * self.size[1] as usize
+    }
+
+    /// Converts texture coordinates to lightmap sample coordinates.
+    pub fn lightmap_coords(&self, tex_coords: [f32; 2]) -> [f32; 2] {
+        [
+            (tex_coords[0] - self.mins[0] as f32) / LIGHTMAP_SCALE,
+            (tex_coords[1] - self.mins[1] as f32) / LIGHTMAP_SCALE,
+        ]
+    }
+}
+
+/// Plane of the face as normal and distance, flipped to the face's side.
+pub fn face_plane(level: &Level<'_>, face: &Face) -> ParsingResult<([f32; 3], f32)> {
+    let plane = level
+        .planes
+        .get(usize::from(face.plane_id.get()))
+        .ok_or(ParsingError::OutOfRange("bsp face plane"))?;
+    let normal = vec3(&plane.normal);
+    let distance = plane.distance.get();
+
+    if face.plane_side.get() != 0 {
+        Ok((normal.map(|v| -v), -distance))
+    } else {
+        Ok((normal, distance))
+    }
+}
+
+/// Calls `f` with every vertex of the face in winding order.
+pub fn face_vertices(
+    level: &Level<'_>,
+    face: &Face,
+    mut f: impl FnMut([f32; 3]),
+) -> ParsingResult<()> {
+    let first = face.first_surfedge_id.get() as usize;
+    let count = usize::from(face.surfedges_num.get());
+    let surfedges = level
+        .surfedges
+        .get(first..first + count)
+        .ok_or(ParsingError::OutOfRange("bsp face surfedges"))?;
+
+    for surfedge in surfedges {
+        let surfedge = surfedge.get();
+        let edge = level
+            .edges
+            .get(surfedge.unsigned_abs() as usize)
+            .ok_or(ParsingError::OutOfRange("bsp face edge"))?;
+        let vertex_id = if surfedge >= 0 { edge[0] } else { edge[1] };
+        let vertex = level
+            .vertices
+            .get(usize::from(vertex_id.get()))
+            .ok_or(ParsingError::OutOfRange("bsp face vertex"))?;
+        f(vec3(vertex));
+    }
+
+    Ok(())
+}
+
+/// Vertices of the face in winding order.
+pub fn face_polygon(level: &Level<'_>, face: &Face) -> ParsingResult<Vec<[f32; 3]>> {
+    let mut polygon = Vec::with_capacity(usize::from(face.surfedges_num.get()));
+    face_vertices(level, face, |vertex| polygon.push(vertex))?;
+    Ok(polygon)
+}
+
+/// Texture mapping info of the face.
+pub fn face_texture_info<'a>(level: &Level<'a>, face: &Face) -> ParsingResult<&'a TextureInfo> {
+    level
+        .texture_infos
+        .get(usize::from(face.texture_info_id.get()))
+        .ok_or(ParsingError::OutOfRange("bsp face texture info"))
+}
+
+/// Mip texture the face is painted with.
+pub fn face_texture<'l, 'a>(
+    level: &'l Level<'a>,
+    face: &Face,
+) -> ParsingResult<&'l MipTexture<'a>> {
+    let texture_info = face_texture_info(level, face)?;
+    level
+        .textures
+        .get(texture_info.texture_id.get() as usize)
+        .ok_or(ParsingError::OutOfRange("bsp face texture"))
+}
+
+/// Texture coordinates (in texels) of a point.
+pub fn texture_coords(texture_info: &TextureInfo, point: [f32; 3]) -> [f32; 2] {
+    [
+        dot(point, vec3(&texture_info.s)) + texture_info.s_shift.get(),
+        dot(point, vec3(&texture_info.t)) + texture_info.t_shift.get(),
+    ]
+}
+
+/// Whether the face carries a lightmap.
+pub fn has_lightmap(level: &Level<'_>, face: &Face) -> ParsingResult<bool> {
+    let texture_info = face_texture_info(level, face)?;
+    Ok(texture_info.flags.get() & TEXTURE_SPECIAL == 0 && face.lightmap_offset.get() != u32::MAX)
+}
+
+/// Lightmap extents of the face.
+pub fn lightmap_extents(level: &Level<'_>, face: &Face) -> ParsingResult<LightmapExtents> {
+    let texture_info = face_texture_info(level, face)?;
+    let axes = [
+        (vec3(&texture_info.s), texture_info.s_shift.get()),
+        (vec3(&texture_info.t), texture_info.t_shift.get()),
+    ];
+
+    // Extents are computed in double precision like the compile tools do,
+    // otherwise rounding may pick a different lightmap size.
+    let mut mins = [f64::MAX; 2];
+    let mut maxs = [f64::MIN; 2];
+    face_vertices(level, face, |vertex| {
+        for (axis, (vector, shift)) in axes.iter().enumerate() {
+            let coord = (0..3)
+                .map(|i| f64::from(vertex[i]) * f64::from(vector[i]))
+                .sum::<f64>()
+                + f64::from(*shift);
+            mins[axis] = mins[axis].min(coord);
+            maxs[axis] = maxs[axis].max(coord);
+        }
+    })?;
+
+    if mins[0] > maxs[0] {
+        return Err(ParsingError::Invalid("bsp face without vertices"));
+    }
+
+    let scale = f64::from(LIGHTMAP_SCALE);
+    let mut extents = LightmapExtents {
+        mins: [0; 2],
+        size: [0; 2],
+    };
+    for axis in 0..2 {
+        let min = (mins[axis] / scale).floor() as i32;
+        let max = (maxs[axis] / scale).ceil() as i32;
+        extents.mins[axis] = min * LIGHTMAP_SCALE as i32;
+        extents.size[axis] =
+            u32::try_from(max - min + 1).map_err(|_| ParsingError::Invalid("bsp face extents"))?;
+    }
+
+    Ok(extents)
+}
@@ -0,0 +1,189 @@
+use crate::{
+    bsp::{Level, lightstyle::LIGHTSTYLE_RATE},
+    common::cstring_bytes,
+    error::{ParsingError, ParsingResult},
+};
+
+/// Maximum number of frames in an animated texture sequence.
+pub const MAX_ANIMATION_FRAMES: usize = 10;
+/// Texture animation frames advanced per second.
+pub const TEXTURE_ANIMATION_RATE: f32 = LIGHTSTYLE_RATE;
+
+/// Primary and alternate frame slots of an animation being grouped.
+type FrameSlots = [[Option<usize>; MAX_ANIMATION_FRAMES]; 2];
+
+/// Meaning given to a texture by its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureKind {
+    /// Regular texture.
+    Normal,
+    /// Warped liquid surface (`!` or `*` prefix, or `laser` or `water` prefix).
+    Water,
+    /// Animated texture frame (`+0`-`+9`, or `+a`-`+j` for the alternate sequence).
+    Animated {
+        /// Frame number within the sequence.
+        frame: u8,
+        /// Whether the frame belongs to the alternate sequence.
+        alternate: bool,
+    },
+    /// Randomly tiled texture variant (`-0`-`-9`).
+    RandomTiled {
+        /// Variant number.
+        variant: u8,
+    },
+    /// Alpha-tested texture, palette index 255 is transparent (`{` prefix).
+    Masked,
+    /// Sky surface (`sky` prefix).
+    Sky,
+    /// Conveyor texture (`scroll` prefix).
+    Scroll,
+    /// Trigger volume brush (`aaatrigger`).
+    Trigger,
+    /// Clip brush (`clip`).
+    Clip,
+    /// Origin brush (`origin`).
+    Origin,
+    /// Translucent texture (`translucent` prefix).
+    Translucent,
+}
+
+/// Texture name split into its meaning and base name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureClass<'a> {
+    /// Meaning of the name.
+    pub kind: TextureKind,
+    /// Name without the special prefix.
+    pub base_name: &'a [u8],
+}
+
+/// Classifies a texture name (e.g. `MipTextureHeader::name`).
+///
+/// Sky and liquid names follow `Mod_LoadFaces`, with the `*` liquid prefix
+/// of the compile tools. Names are compared case-insensitively.
+pub fn classify(name: &[u8]) -> TextureClass<'_> {
+    let name = cstring_bytes(name);
+    let class = |kind, base_name| TextureClass { kind, base_name };
+
+    match name {
+        [b'!' | b'*', rest @ ..] => class(TextureKind::Water, rest),
+        [b'{', rest @ ..] => class(TextureKind::Masked, rest),
+        [b'+', frame, rest @ ..] => match frame.to_ascii_lowercase() {
+            frame @ b'0'..=b'9' => class(
+                TextureKind::Animated {
+                    frame: frame - b'0',
+                    alternate: false,
+                },
+                rest,
+            ),
+            frame @ b'a'..=b'j' => class(
+                TextureKind::Animated {
+                    frame: frame - b'a',
+                    alternate: true,
+                },
+                rest,
+            ),
+            _ => class(TextureKind::Normal, name),
+        },
+        [b'-', variant @ b'0'..=b'9', rest @ ..] => class(
+            TextureKind::RandomTiled {
+                variant: variant - b'0',
+            },
+            rest,
+        ),
+        _ if starts_with_ignore_case(name, b"sky") => class(TextureKind::Sky, name),
+        _ if starts_with_ignore_case(name, b"laser") || starts_with_ignore_case(name, b"water") => {
+            class(TextureKind::Water, name)
+        }
+        _ if name.eq_ignore_ascii_case(b"aaatrigger") => class(TextureKind::Trigger, name),
+        _ if name.eq_ignore_ascii_case(b"clip") => class(TextureKind::Clip, name),
+        _ if name.eq_ignore_ascii_case(b"origin") => class(TextureKind::Origin, name),
+        _ if starts_with_ignore_case(name, b"scroll") => class(TextureKind::Scroll, name),
+        _ if starts_with_ignore_case(name, b"translucent") => class(TextureKind::Translucent, name),
+        _ => class(TextureKind::Normal, name),
+    }
+}
+
+/// Animated texture sequence built from `+` textures sharing a base name.
+#[derive(Debug, Clone)]
+pub struct TextureAnimation<'a> {
+    /// Base name shared by the frames.
+    pub base_name: &'a [u8],
+    /// Texture indices of the primary sequence, in frame order.
+    pub frames: Vec<usize>,
+    /// Texture indices of the alternate sequence, in frame order.
+    pub alternate_frames: Vec<usize>,
+}
+
+impl TextureAnimation<'_> {
+    /// Texture index shown at time `t` (in seconds) (`R_TextureAnimation`).
+    ///
+    /// Falls back to the other sequence if the requested one is empty.
+    pub fn frame(&self, t: f32, alternate: bool) -> Option<usize> {
+        let use_alternate = alternate && !self.alternate_frames.is_empty();
+        let frames = if use_alternate || self.frames.is_empty() {
+            &self.alternate_frames
+        } else {
+            &self.frames
+        };
+        if frames.is_empty() {
+            return None;
+        }
+
+        let frame = (t.max(0.0) * TEXTURE_ANIMATION_RATE) as usize % frames.len();
+        Some(frames[frame])
+    }
+}
+
+/// Groups animated textures of the level into sequences (`Mod_LoadTextures`).
+///
+/// Fails if a sequence misses a frame, like the engine does.
+pub fn texture_animations<'a>(level: &Level<'a>) -> ParsingResult<Vec<TextureAnimation<'a>>> {
+    let mut groups: Vec<(&'a [u8], FrameSlots)> = Vec::new();
+
+    for (texture_id, texture) in level.textures.iter().enumerate() {
+        let class = classify(&texture.header.name);
+        let TextureKind::Animated { frame, alternate } = class.kind else {
+            continue;
+        };
+
+        let group = match groups
+            .iter()
+            .position(|(name, _)| name.eq_ignore_ascii_case(class.base_name))
+        {
+            Some(group) => group,
+            None => {
+                groups.push((class.base_name, [[None; MAX_ANIMATION_FRAMES]; 2]));
+                groups.len() - 1
+            }
+        };
+        groups[group].1[usize::from(alternate)][usize::from(frame)] = Some(texture_id);
+    }
+
+    groups
+        .into_iter()
+        .map(|(base_name, [frames, alternate_frames])| {
+            Ok(TextureAnimation {
+                base_name,
+                frames: collect_frames(&frames)?,
+                alternate_frames: collect_frames(&alternate_frames)?,
+            })
+        })
+        .collect()
+}
+
+fn collect_frames(frames: &[Option<usize>]) -> ParsingResult<Vec<usize>> {
+    let count = frames
+        .iter()
+        .rposition(Option::is_some)
+        .map_or(0, |last| last + 1);
+
+    frames[..count]
+        .iter()
+        .map(|frame| frame.ok_or(ParsingError::Invalid("bsp texture animation frame")))
+        .collect()
+}
+
+fn starts_with_ignore_case(name: &[u8], prefix: &[u8]) -> bool {
+    name.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
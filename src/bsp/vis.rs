@@ -0,0 +1,95 @@
+use crate::{
+    bsp::{Level, NodeChild, query::world_head_node},
+    error::{ParsingError, ParsingResult},
+};
+
+/// Potentially visible set of a leaf.
+#[derive(Debug, Clone)]
+pub struct Pvs {
+    /// Decompressed visibility bits, bit `n` stands for leaf `n + 1`.
+    pub bits: Vec<u8>,
+}
+
+impl Pvs {
+    /// Whether the leaf is potentially visible.
+    pub fn is_visible(&self, leaf_id: usize) -> bool {
+        let Some(bit) = leaf_id.checked_sub(1) else {
+            return false;
+        };
+        self.bits
+            .get(bit >> 3)
+            .is_some_and(|byte| byte & (1 << (bit & 7)) != 0)
+    }
+}
+
+/// Index of the world leaf containing the point.
+pub fn point_leaf(level: &Level<'_>, point: [f32; 3]) -> ParsingResult<usize> {
+    let mut child = world_head_node(level).ok_or(ParsingError::OutOfRange("bsp world model"))?;
+    for _ in 0..=level.nodes.len() {
+        match child {
+            NodeChild::Leaf(leaf_id) => return Ok(leaf_id),
+            NodeChild::Node(node_id) => {
+                let node = level
+                    .nodes
+                    .get(node_id)
+                    .ok_or(ParsingError::OutOfRange("bsp node"))?;
+                let plane = level
+                    .planes
+                    .get(node.plane_id.get() as usize)
+                    .ok_or(ParsingError::OutOfRange("bsp plane"))?;
+                child = node.child(usize::from(plane.distance_to(point) < 0.0));
+            }
+        }
+    }
+
+    Err(ParsingError::Invalid("bsp node tree"))
+}
+
+/// Number of leaves covered by visibility data.
+pub fn vis_leaves_num(level: &Level<'_>) -> usize {
+    level
+        .models
+        .first()
+        .and_then(|model| usize::try_from(model.vis_leafs.get()).ok())
+        .unwrap_or(0)
+}
+
+/// Decompresses the PVS of a leaf (`Mod_DecompressVis`).
+///
+/// Everything is visible from the solid leaf and from maps without visdata.
+pub fn leaf_pvs(level: &Level<'_>, leaf_id: usize) -> ParsingResult<Pvs> {
+    let leaf = level
+        .leaves
+        .get(leaf_id)
+        .ok_or(ParsingError::OutOfRange("bsp leaf"))?;
+    let row = vis_leaves_num(level).div_ceil(8);
+
+    let vis_offset = leaf.vis_offset.get();
+    if leaf_id == 0 || vis_offset < 0 || level.visdata.is_empty() {
+        return Ok(Pvs {
+            bits: vec![0xFF; row],
+        });
+    }
+
+    let mut input = level
+        .visdata
+        .get(vis_offset as usize..)
+        .ok_or(ParsingError::OutOfRange("bsp visdata"))?;
+    let mut bits = Vec::with_capacity(row);
+    while bits.len() < row {
+        match input {
+            [0, count, rest @ ..] => {
+                let count = usize::from(*count).min(row - bits.len());
+                bits.resize(bits.len() + count, 0);
+                input = rest;
+            }
+            [byte, rest @ ..] if *byte != 0 => {
+                bits.push(*byte);
+                input = rest;
+            }
+            _ => return Err(ParsingError::OutOfRange("bsp visdata")),
+        }
+    }
+
+    Ok(Pvs { bits })
+}
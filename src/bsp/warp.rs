@@ -0,0 +1,156 @@
+use std::f32::consts::TAU;
+
+use crate::{
+    bsp::{
+        Face, Level, TextureInfo,
+        surface::{face_polygon, face_texture, face_texture_info},
+        texture_name::{TextureKind, classify},
+    },
+    error::{ParsingError, ParsingResult},
+    math::{dot, lerp, vec3},
+};
+
+/// Size of the polygons warped surfaces are cut into.
+pub const SUBDIVIDE_SIZE: f32 = 64.0;
+/// Maximum number of vertices of a polygon being subdivided.
+pub const MAX_SUBDIVIDE_VERTICES: usize = 60;
+/// Turbulence cycles per second (`TURBSCALE` in radians).
+const TURBULENCE_SCALE: f32 = 256.0 / TAU;
+/// Amplitude of the turbulence, in texels.
+const TURBULENCE_AMPLITUDE: f32 = 8.0;
+
+/// How a surface is drawn after subdivision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarpKind {
+    /// Turbulent liquid (`!`, `*`, `laser` and `water` textures).
+    Turbulent,
+    /// Sky (`sky` textures).
+    Sky,
+}
+
+/// Vertex of a subdivided polygon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WarpVertex {
+    /// Position.
+    pub position: [f32; 3],
+    /// Texture coordinates in texels, without the texture shift.
+    pub tex_coords: [f32; 2],
+}
+
+/// Warp kind of a texture name (see `texture_name::classify`), `None` for
+/// regular surfaces.
+pub fn warp_kind(name: &[u8]) -> Option<WarpKind> {
+    match classify(name).kind {
+        TextureKind::Sky => Some(WarpKind::Sky),
+        TextureKind::Water => Some(WarpKind::Turbulent),
+        _ => None,
+    }
+}
+
+/// Warp kind of a face, `None` for regular surfaces.
+pub fn face_warp_kind(level: &Level<'_>, face: &Face) -> ParsingResult<Option<WarpKind>> {
+    let texture = face_texture(level, face)?;
+    Ok(warp_kind(&texture.header.name))
+}
+
+/// Subdivides a face into `SUBDIVIDE_SIZE` polygons (`GL_SubdivideSurface`).
+pub fn subdivide_face(level: &Level<'_>, face: &Face) -> ParsingResult<Vec<Vec<WarpVertex>>> {
+    let polygon = face_polygon(level, face)?;
+    let texture_info = face_texture_info(level, face)?;
+    subdivide_polygon(&polygon, texture_info, SUBDIVIDE_SIZE)
+}
+
+/// Recursively cuts a polygon on a `size` grid (`SubdividePolygon`).
+///
+/// Polygons are returned in the order the engine links them, the last one
+/// created first.
+pub fn subdivide_polygon(
+    polygon: &[[f32; 3]],
+    texture_info: &TextureInfo,
+    size: f32,
+) -> ParsingResult<Vec<Vec<WarpVertex>>> {
+    let mut polygons = Vec::new();
+    subdivide_r(polygon, texture_info, size, &mut polygons)?;
+    polygons.reverse();
+    Ok(polygons)
+}
+
+/// Texture coordinates of a turbulent vertex at time `t` (`EmitWaterPolys`).
+///
+/// The result is normalized, `1.0` spanning 64 texels.
+pub fn turbulent_coords(tex_coords: [f32; 2], t: f32) -> [f32; 2] {
+    let [os, ot] = tex_coords;
+    let turb = |v: f32| {
+        let index = ((v * 0.125 + t) * TURBULENCE_SCALE) as i32 & 255;
+        TURBULENCE_AMPLITUDE * (index as f32 * TAU / 256.0).sin()
+    };
+
+    [(os + turb(ot)) / 64.0, (ot + turb(os)) / 64.0]
+}
+
+fn subdivide_r(
+    polygon: &[[f32; 3]],
+    texture_info: &TextureInfo,
+    size: f32,
+    polygons: &mut Vec<Vec<WarpVertex>>,
+) -> ParsingResult<()> {
+    if polygon.len() > MAX_SUBDIVIDE_VERTICES {
+        return Err(ParsingError::Invalid("bsp subdivided polygon vertices"));
+    }
+
+    let mut mins = [f32::MAX; 3];
+    let mut maxs = [f32::MIN; 3];
+    for vertex in polygon {
+        for axis in 0..3 {
+            mins[axis] = mins[axis].min(vertex[axis]);
+            maxs[axis] = maxs[axis].max(vertex[axis]);
+        }
+    }
+
+    for axis in 0..3 {
+        let mid = size * ((mins[axis] + maxs[axis]) * 0.5 / size + 0.5).floor();
+        if maxs[axis] - mid < 8.0 || mid - mins[axis] < 8.0 {
+            continue;
+        }
+
+        let mut front = Vec::with_capacity(polygon.len() + 1);
+        let mut back = Vec::with_capacity(polygon.len() + 1);
+        for (i, &vertex) in polygon.iter().enumerate() {
+            let next = polygon[(i + 1) % polygon.len()];
+            let dist = vertex[axis] - mid;
+            let next_dist = next[axis] - mid;
+
+            if dist >= 0.0 {
+                front.push(vertex);
+            }
+            if dist <= 0.0 {
+                back.push(vertex);
+            }
+            if dist == 0.0 || next_dist == 0.0 {
+                continue;
+            }
+            if (dist > 0.0) != (next_dist > 0.0) {
+                let clip = lerp(vertex, next, dist / (dist - next_dist));
+                front.push(clip);
+                back.push(clip);
+            }
+        }
+
+        subdivide_r(&front, texture_info, size, polygons)?;
+        return subdivide_r(&back, texture_info, size, polygons);
+    }
+
+    let s = vec3(&texture_info.s);
+    let t = vec3(&texture_info.t);
+    polygons.push(
+        polygon
+            .iter()
+            .map(|&position| WarpVertex {
+                position,
+                tex_coords: [dot(position, s), dot(position, t)],
+            })
+            .collect(),
+    );
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::{
+    bsp::Level,
+    common::cstring_bytes,
+    error::{ParsingError, ParsingResult},
+    util::parse_f32,
+};
+
+/// Directory holding detail textures, relative to the game directory.
+pub const DETAIL_DIR: &str = "gfx";
+/// Detail texture image extension.
+pub const DETAIL_EXTENSION: &str = "tga";
+
+/// Entry of a `<map>_detail.txt` list.
+#[derive(Debug, Clone)]
+pub struct DetailTexture<'a> {
+    /// Level texture name (not guaranteed UTF-8).
+    pub texture: &'a [u8],
+    /// Detail texture name relative to `DETAIL_DIR`, e.g. `detail/metal1`.
+    pub detail: &'a [u8],
+    /// Detail texture repeats per base texture, along s and t.
+    pub scale: [f32; 2],
+}
+
+impl DetailTexture<'_> {
+    /// Path of the detail texture image relative to the game directory.
+    pub fn path(&self) -> String {
+        format!(
+            "{DETAIL_DIR}/{}.{DETAIL_EXTENSION}",
+            String::from_utf8_lossy(self.detail)
+        )
+    }
+}
+
+/// Parses a detail texture list (`texture detail xscale yscale` per line).
+///
+/// Empty lines and `//` comments are skipped.
+pub fn detail_textures(bytes: &[u8]) -> ParsingResult<Vec<DetailTexture<'_>>> {
+    let mut textures = Vec::new();
+
+    for line in bytes.split(|&b| b == b'\n') {
+        let line = match line.windows(2).position(|w| w == b"//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut tokens = line
+            .split(u8::is_ascii_whitespace)
+            .filter(|token| !token.is_empty());
+        let Some(texture) = tokens.next() else {
+            continue;
+        };
+        let detail = tokens
+            .next()
+            .ok_or(ParsingError::OutOfRange("detail texture name"))?;
+        let mut scale = [0.0; 2];
+        for slot in &mut scale {
+            let token = tokens
+                .next()
+                .ok_or(ParsingError::OutOfRange("detail texture scale"))?;
+            *slot = parse_f32(token).ok_or(ParsingError::Invalid("detail texture scale"))?;
+        }
+
+        textures.push(DetailTexture {
+            texture,
+            detail,
+            scale,
+        });
+    }
+
+    Ok(textures)
+}
+
+/// Detail texture of each `Level::textures` entry, matched case-insensitively.
+///
+/// When a texture is listed twice, the first entry wins.
+pub fn level_detail_textures<'d, 'a>(
+    level: &Level<'_>,
+    details: &'d [DetailTexture<'a>],
+) -> Vec<Option<&'d DetailTexture<'a>>> {
+    level
+        .textures
+        .iter()
+        .map(|texture| {
+            let name = cstring_bytes(&texture.header.name);
+            details
+                .iter()
+                .find(|detail| detail.texture.eq_ignore_ascii_case(name))
+        })
+        .collect()
+}
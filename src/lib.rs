@@ -1,8 +1,15 @@
 pub mod bsp;
 pub mod common;
+pub mod detail;
 pub mod error;
 pub mod mdl;
+pub mod nod;
+pub mod pointfile;
+pub mod prt;
+pub mod res;
+pub mod skybox;
 pub mod texture;
 pub mod wad;
 
+pub(crate) mod math;
 pub(crate) mod util;
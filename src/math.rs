@@ -0,0 +1,50 @@
+use crate::common::{Vec3f, Vec3s};
+
+pub fn vec3(v: &Vec3f) -> [f32; 3] {
+    [v[0].get(), v[1].get(), v[2].get()]
+}
+
+pub fn vec3s(v: &Vec3s) -> [f32; 3] {
+    [
+        f32::from(v[0].get()),
+        f32::from(v[1].get()),
+        f32::from(v[2].get()),
+    ]
+}
+
+pub fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn mad(a: [f32; 3], b: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] + b[0] * s, a[1] + b[1] * s, a[2] + b[2] * s]
+}
+
+pub fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    mad(a, sub(b, a), t)
+}
+
+pub fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len > 0.0 { scale(a, 1.0 / len) } else { a }
+}
@@ -12,6 +12,18 @@ use crate::{
     util::{pixel_size, table_ref},
 };
 
+pub mod anim;
+pub mod blend;
+pub mod body;
+pub mod controller;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod pose;
+pub mod seqgroup;
+pub mod skinning;
+pub mod texmodel;
+pub mod tricmd;
+
 /// MDL magic (GoldSrc).
 pub const MDL_MAGIC: [u8; 4] = *b"IDST";
 /// MDL version (Half-Life 1).
@@ -19,6 +31,26 @@ pub const MDL_VERSION: u32 = 10;
 /// Number of mip levels in a model texture.
 pub const MIP_LEVELS: usize = 4;
 
+/// Texture flag: palette index 255 is transparent.
+pub const STUDIO_NF_MASKED: u32 = 0x40;
+
+/// Motion and controller type: X position.
+pub const STUDIO_X: i32 = 0x1;
+/// Motion and controller type: Y position.
+pub const STUDIO_Y: i32 = 0x2;
+/// Motion and controller type: Z position.
+pub const STUDIO_Z: i32 = 0x4;
+/// Motion and controller type: X rotation.
+pub const STUDIO_XR: i32 = 0x8;
+/// Motion and controller type: Y rotation.
+pub const STUDIO_YR: i32 = 0x10;
+/// Motion and controller type: Z rotation.
+pub const STUDIO_ZR: i32 = 0x20;
+/// Mask of the motion and controller type bits.
+pub const STUDIO_TYPES: i32 = 0x7FFF;
+/// Controller type flag: rotation wraps around 360 degrees.
+pub const STUDIO_RLOOP: i32 = 0x8000;
+
 /// Complete model data loaded from a MDL file.
 pub struct SkeletalModel<'a> {
     /// MDL header.
@@ -0,0 +1,162 @@
+use zerocopy::{
+    FromBytes,
+    little_endian::{I16, U16},
+};
+
+use crate::{
+    error::{ParsingError, ParsingResult},
+    mdl::{Bone, SequenceDesc},
+};
+
+/// Number of animated channels per bone (position X/Y/Z, rotation X/Y/Z).
+pub const ANIM_CHANNELS: usize = 6;
+
+/// Per-bone offsets to the channel value streams (`mstudioanim_t`).
+///
+/// Offsets are relative to the entry itself, `0` meaning the channel isn't animated.
+pub type AnimOffsets = [U16; ANIM_CHANNELS];
+
+/// Compressed animation of a sequence blend.
+#[derive(Debug, Clone, Copy)]
+pub struct SequenceAnim<'a> {
+    /// Bytes starting at the first bone's `AnimOffsets`.
+    pub data: &'a [u8],
+    /// Number of bones.
+    pub bones_num: usize,
+    /// Number of frames.
+    pub frames_num: usize,
+}
+
+impl SequenceAnim<'_> {
+    /// Raw compressed value of a bone channel at a frame, `None` if not animated.
+    ///
+    /// Frames past the last one are clamped.
+    pub fn raw_value(
+        &self,
+        bone_id: usize,
+        channel: usize,
+        frame: usize,
+    ) -> ParsingResult<Option<i16>> {
+        if bone_id >= self.bones_num || channel >= ANIM_CHANNELS {
+            return Err(ParsingError::OutOfRange("mdl anim channel"));
+        }
+        let entry = bone_id * size_of::<AnimOffsets>();
+        let (offsets, _) = self
+            .data
+            .get(entry..)
+            .and_then(|data| AnimOffsets::ref_from_prefix(data).ok())
+            .ok_or(ParsingError::OutOfRange("mdl anim offsets"))?;
+        let offset = usize::from(offsets[channel].get());
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let stream = self
+            .data
+            .get(entry + offset..)
+            .ok_or(ParsingError::OutOfRange("mdl anim values"))?;
+        let (values, _) = <[I16]>::ref_from_prefix_with_elems(stream, stream.len() / 2)
+            .map_err(|_| ParsingError::OutOfRange("mdl anim values"))?;
+
+        // Each span starts with a `valid`/`total` byte pair, followed by
+        // `valid` values; the last one repeats until `total` frames are covered.
+        let span = |pos: usize| -> ParsingResult<(usize, usize)> {
+            let [valid, total] = values
+                .get(pos)
+                .ok_or(ParsingError::OutOfRange("mdl anim span"))?
+                .to_bytes();
+            Ok((usize::from(valid), usize::from(total)))
+        };
+        let value = |pos: usize| -> ParsingResult<i16> {
+            values
+                .get(pos)
+                .map(|value| value.get())
+                .ok_or(ParsingError::OutOfRange("mdl anim value"))
+        };
+
+        let mut k = frame.min(self.frames_num.saturating_sub(1));
+        let mut pos = 0;
+        let (mut valid, mut total) = span(pos)?;
+        // Broken spans restart at their first value, like the engine does.
+        if total < valid {
+            k = 0;
+        }
+        while total <= k {
+            k -= total;
+            pos += valid + 1;
+            (valid, total) = span(pos)?;
+            if total < valid {
+                k = 0;
+            }
+        }
+
+        Ok(Some(if valid > k {
+            value(pos + k + 1)?
+        } else {
+            value(pos + valid)?
+        }))
+    }
+
+    /// Value of a bone channel at a frame, with the bone's default and scale applied.
+    pub fn value(
+        &self,
+        bone: &Bone,
+        bone_id: usize,
+        channel: usize,
+        frame: usize,
+    ) -> ParsingResult<f32> {
+        let raw = self.raw_value(bone_id, channel, frame)?;
+        let default = bone.value[channel].get();
+        Ok(match raw {
+            Some(raw) => default + f32::from(raw) * bone.scale[channel].get(),
+            None => default,
+        })
+    }
+
+    /// All channel values of a bone at a frame.
+    pub fn values(
+        &self,
+        bone: &Bone,
+        bone_id: usize,
+        frame: usize,
+    ) -> ParsingResult<[f32; ANIM_CHANNELS]> {
+        let mut values = [0.0; ANIM_CHANNELS];
+        for (channel, value) in values.iter_mut().enumerate() {
+            *value = self.value(bone, bone_id, channel, frame)?;
+        }
+        Ok(values)
+    }
+}
+
+/// Animation of a sequence blend.
+///
+/// `bytes` is the file holding the animation: the model itself for sequence
+/// group 0.
+pub fn sequence_anim<'a>(
+    bytes: &'a [u8],
+    bones_num: usize,
+    sequence: &SequenceDesc,
+    blend: usize,
+) -> ParsingResult<SequenceAnim<'a>> {
+    let blends_num = usize::try_from(sequence.blends_num.get())
+        .map_err(|_| ParsingError::Invalid("mdl sequence blends"))?;
+    if blend >= blends_num.max(1) {
+        return Err(ParsingError::OutOfRange("mdl sequence blend"));
+    }
+    let frames_num = usize::try_from(sequence.frames_num.get())
+        .map_err(|_| ParsingError::Invalid("mdl sequence frames"))?;
+
+    let offset = usize::try_from(sequence.anim_index.get())
+        .map_err(|_| ParsingError::Invalid("mdl sequence anim"))?;
+    let blend_size = bones_num * size_of::<AnimOffsets>();
+    let data = bytes
+        .get(offset + blend * blend_size..)
+        .filter(|data| data.len() >= blend_size)
+        .ok_or(ParsingError::OutOfRange("mdl sequence anim"))?;
+
+    Ok(SequenceAnim {
+        data,
+        bones_num,
+        frames_num,
+    })
+}
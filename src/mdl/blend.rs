@@ -0,0 +1,84 @@
+use crate::{
+    error::{ParsingError, ParsingResult},
+    math::lerp,
+    mdl::{
+        Bone, STUDIO_XR, STUDIO_YR, STUDIO_ZR, SequenceDesc,
+        anim::sequence_anim,
+        controller::setting,
+        pose::{BonePose, pose, quaternion_slerp},
+    },
+};
+
+/// Duration of the crossfade from the previous sequence, in seconds.
+pub const CROSSFADE_TIME: f32 = 0.2;
+
+/// Blender settings of an entity, `0..=255` spanning each blend range.
+pub type Blending = [u8; 2];
+
+/// Blender setting of a sequence for a value in blend units (degrees for
+/// rotations), like `SetBlending`.
+///
+/// Returns `None` if the sequence has no such blender.
+pub fn blend_setting(sequence: &SequenceDesc, blender: usize, value: f32) -> Option<u8> {
+    let ty = sequence.blend_type.get(blender)?.get();
+    if ty == 0 {
+        return None;
+    }
+    let start = sequence.blend_start[blender].get();
+    let end = sequence.blend_end[blender].get();
+    let rotation = ty & (STUDIO_XR | STUDIO_YR | STUDIO_ZR) != 0;
+
+    Some(setting(value, start, end, rotation, u8::MAX))
+}
+
+/// Interpolates two poses, `s` being the weight of `to` (`StudioSlerpBones`).
+pub fn blend_poses(from: &[BonePose], to: &[BonePose], s: f32) -> ParsingResult<Vec<BonePose>> {
+    if from.len() != to.len() {
+        return Err(ParsingError::Invalid("mdl blended pose bones"));
+    }
+    let s = s.clamp(0.0, 1.0);
+
+    Ok(from
+        .iter()
+        .zip(to)
+        .map(|(from, to)| BonePose {
+            position: lerp(from.position, to.position, s),
+            rotation: quaternion_slerp(from.rotation, to.rotation, s),
+        })
+        .collect())
+}
+
+/// Pose of a sequence mixing its blends (`StudioSetupBones`).
+///
+/// Two blends are mixed by the first blender, four blends by the first
+/// blender in pairs then by the second blender.
+pub fn blended_pose(
+    bytes: &[u8],
+    bones: &[Bone],
+    sequence: &SequenceDesc,
+    frame: f32,
+    blending: Blending,
+    adjustments: &[f32],
+) -> ParsingResult<Vec<BonePose>> {
+    let blend_pose = |blend: usize| {
+        let anim = sequence_anim(bytes, bones.len(), sequence, blend)?;
+        pose(&anim, bones, sequence, frame, adjustments)
+    };
+    let [s, t] = blending.map(|setting| f32::from(setting) / 255.0);
+
+    match sequence.blends_num.get() {
+        4.. => {
+            let first = blend_poses(&blend_pose(0)?, &blend_pose(1)?, s)?;
+            let second = blend_poses(&blend_pose(2)?, &blend_pose(3)?, s)?;
+            blend_poses(&first, &second, t)
+        }
+        2.. => blend_poses(&blend_pose(0)?, &blend_pose(1)?, s),
+        _ => blend_pose(0),
+    }
+}
+
+/// Weight of the previous sequence `elapsed` seconds after a sequence change,
+/// to blend the previous pose over the current one with `blend_poses`.
+pub fn crossfade_weight(elapsed: f32) -> f32 {
+    (1.0 - elapsed / CROSSFADE_TIME).clamp(0.0, 1.0)
+}
@@ -0,0 +1,85 @@
+use crate::{
+    error::{ParsingError, ParsingResult},
+    mdl::{BodyPart, SkeletalModel},
+};
+
+/// Submodel index of each body part selected by a `body` value.
+pub fn body_models(bodyparts: &[BodyPart], body: u32) -> ParsingResult<Vec<usize>> {
+    bodyparts
+        .iter()
+        .map(|bodypart| {
+            let (base, count) = bodypart_range(bodypart)?;
+            Ok((body / base % count) as usize)
+        })
+        .collect()
+}
+
+/// `body` value selecting a submodel index for each body part.
+pub fn encode_body(bodyparts: &[BodyPart], models: &[usize]) -> ParsingResult<u32> {
+    if bodyparts.len() != models.len() {
+        return Err(ParsingError::Invalid("mdl body models"));
+    }
+
+    bodyparts
+        .iter()
+        .zip(models)
+        .try_fold(0u32, |body, (bodypart, &model)| {
+            let (base, count) = bodypart_range(bodypart)?;
+            let model = u32::try_from(model)
+                .ok()
+                .filter(|&model| model < count)
+                .ok_or(ParsingError::OutOfRange("mdl body model"))?;
+            model
+                .checked_mul(base)
+                .and_then(|value| body.checked_add(value))
+                .ok_or(ParsingError::NumberOverflow("mdl body"))
+        })
+}
+
+/// `body` value with the submodel of one body part replaced (`SetBodygroup`).
+pub fn set_bodygroup(
+    bodyparts: &[BodyPart],
+    body: u32,
+    bodypart: usize,
+    model: usize,
+) -> ParsingResult<u32> {
+    let mut models = body_models(bodyparts, body)?;
+    *models
+        .get_mut(bodypart)
+        .ok_or(ParsingError::OutOfRange("mdl bodypart"))? = model;
+    encode_body(bodyparts, &models)
+}
+
+/// Texture index of a mesh `skin_ref` in skin family `skin`.
+///
+/// Out of range families fall back to family 0, like the engine does.
+pub fn skin_texture(model: &SkeletalModel<'_>, skin: usize, skin_ref: i32) -> ParsingResult<usize> {
+    let skins = &model.skins;
+    let skin = if skin < skins.skin_families_num as usize {
+        skin
+    } else {
+        0
+    };
+    let skin_ref = usize::try_from(skin_ref)
+        .ok()
+        .filter(|&skin_ref| skin_ref < skins.skin_refs_num as usize)
+        .ok_or(ParsingError::OutOfRange("mdl mesh skin ref"))?;
+
+    skins
+        .refs
+        .get(skin * skins.skin_refs_num as usize + skin_ref)
+        .map(|texture_id| usize::from(texture_id.get()))
+        .filter(|&texture_id| texture_id < model.textures.len())
+        .ok_or(ParsingError::OutOfRange("mdl skin texture"))
+}
+
+fn bodypart_range(bodypart: &BodyPart) -> ParsingResult<(u32, u32)> {
+    let base = u32::try_from(bodypart.base.get())
+        .ok()
+        .filter(|&base| base > 0)
+        .ok_or(ParsingError::Invalid("mdl bodypart base"))?;
+    let count = Some(bodypart.models_num.get())
+        .filter(|&count| count > 0)
+        .ok_or(ParsingError::Invalid("mdl bodypart models"))?;
+    Ok((base, count))
+}
@@ -0,0 +1,137 @@
+use crate::mdl::{
+    BoneController, STUDIO_RLOOP, STUDIO_TYPES, STUDIO_X, STUDIO_XR, STUDIO_Y, STUDIO_YR, STUDIO_Z,
+    STUDIO_ZR,
+};
+
+/// Number of regular controllers of an entity.
+pub const CONTROLLERS_NUM: usize = 4;
+/// Controller index of the mouth.
+pub const MOUTH_CONTROLLER: usize = 4;
+/// Largest mouth setting.
+pub const MOUTH_MAX: u8 = 64;
+
+/// Controller settings of an entity, as sent over the network.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Controllers {
+    /// Regular controller settings, `0..=255` spanning the controller range.
+    pub controllers: [u8; CONTROLLERS_NUM],
+    /// Mouth setting, `0..=MOUTH_MAX` spanning the controller range.
+    pub mouth: u8,
+}
+
+impl Controllers {
+    /// Settings with every controller at its rest value.
+    pub fn rest(bone_controllers: &[BoneController]) -> Self {
+        let mut controllers = Self::default();
+        for controller in bone_controllers {
+            let rest = controller.rest.get().clamp(0, 255) as u8;
+            match usize::try_from(controller.index.get()) {
+                Ok(MOUTH_CONTROLLER) => controllers.mouth = rest.min(MOUTH_MAX),
+                Ok(index) if index < CONTROLLERS_NUM => controllers.controllers[index] = rest,
+                _ => {}
+            }
+        }
+        controllers
+    }
+
+    /// Sets controller `index` to a value in controller units (degrees for
+    /// rotations), like `SetController` and `SetMouth`.
+    ///
+    /// Returns the value actually applied after quantization, or `None` if
+    /// no bone controller uses `index`.
+    pub fn set(
+        &mut self,
+        bone_controllers: &[BoneController],
+        index: usize,
+        value: f32,
+    ) -> Option<f32> {
+        let controller = bone_controllers
+            .iter()
+            .find(|controller| usize::try_from(controller.index.get()) == Ok(index))?;
+        let start = controller.start.get();
+        let end = controller.end.get();
+
+        let rotation = index != MOUTH_CONTROLLER
+            && controller.ty.get() & (STUDIO_XR | STUDIO_YR | STUDIO_ZR) != 0;
+        let max = if index == MOUTH_CONTROLLER {
+            MOUTH_MAX
+        } else {
+            u8::MAX
+        };
+        let setting = setting(value, start, end, rotation, max);
+        match index {
+            MOUTH_CONTROLLER => self.mouth = setting,
+            index => *self.controllers.get_mut(index)? = setting,
+        }
+
+        Some(f32::from(setting) / f32::from(max) * (end - start) + start)
+    }
+}
+
+/// Channel adjustments of each bone controller (`StudioCalcBoneAdj`).
+///
+/// Rotations are in radians. The result is indexed like `bone_controllers`,
+/// as referenced by `Bone::bone_controller`.
+pub fn bone_adjustments(
+    bone_controllers: &[BoneController],
+    controllers: &Controllers,
+) -> Vec<f32> {
+    bone_controllers
+        .iter()
+        .map(|controller| {
+            let start = controller.start.get();
+            let end = controller.end.get();
+            let ty = controller.ty.get();
+
+            let value = match usize::try_from(controller.index.get()) {
+                Ok(MOUTH_CONTROLLER) => {
+                    let s = (f32::from(controllers.mouth) / f32::from(MOUTH_MAX)).min(1.0);
+                    (1.0 - s) * start + s * end
+                }
+                Ok(index) if index < CONTROLLERS_NUM => {
+                    let setting = f32::from(controllers.controllers[index]);
+                    if ty & STUDIO_RLOOP != 0 {
+                        setting * (360.0 / 256.0) + start
+                    } else {
+                        let s = setting / 255.0;
+                        (1.0 - s) * start + s * end
+                    }
+                }
+                _ => return 0.0,
+            };
+
+            match ty & STUDIO_TYPES {
+                STUDIO_XR | STUDIO_YR | STUDIO_ZR => value.to_radians(),
+                STUDIO_X | STUDIO_Y | STUDIO_Z => value,
+                _ => 0.0,
+            }
+        })
+        .collect()
+}
+
+/// Quantizes `value` into `0..=max` over `start..end` (`SetController`).
+///
+/// Rotations in degrees are first wrapped towards the range.
+pub(crate) fn setting(value: f32, start: f32, end: f32, rotation: bool, max: u8) -> u8 {
+    let mut value = value;
+    if rotation {
+        if end < start {
+            value = -value;
+        }
+        if start + 359.0 >= end {
+            let mid = (start + end) / 2.0;
+            if value > mid + 180.0 {
+                value -= 360.0;
+            }
+            if value < mid - 180.0 {
+                value += 360.0;
+            }
+        } else if value > 360.0 {
+            value -= (value / 360.0).trunc() * 360.0;
+        } else if value < 0.0 {
+            value += ((value / -360.0).trunc() + 1.0) * 360.0;
+        }
+    }
+
+    (f32::from(max) * (value - start) / (end - start)).clamp(0.0, f32::from(max)) as u8
+}
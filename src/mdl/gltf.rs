@@ -0,0 +1,554 @@
+use std::fmt::Write;
+
+use crate::{
+    common::cstring_bytes,
+    error::{ParsingError, ParsingResult},
+    math::{normalize, vec3},
+    mdl::{
+        Bone, Model, STUDIO_NF_MASKED, SkeletalModel, Texture,
+        anim::sequence_anim,
+        body::skin_texture,
+        bodypart_models, model_meshes, model_vertex_bones,
+        pose::{BoneMatrix, BonePose, angle_quaternion, bone_matrices, pose},
+        seqgroup::sequence_anim_bytes,
+        skinning::{skin_normals, skin_vertices, tex_coord_scale},
+        texmodel::ModelFiles,
+        tricmd::mesh_triangles,
+    },
+};
+
+/// Binary glTF magic.
+pub const GLB_MAGIC: [u8; 4] = *b"glTF";
+/// Binary glTF version.
+pub const GLB_VERSION: u32 = 2;
+/// Rotation of the scene root turning the Z-up model into a Y-up scene.
+pub const Z_UP_ROTATION: [f32; 4] = [
+    -std::f32::consts::FRAC_1_SQRT_2,
+    0.0,
+    0.0,
+    std::f32::consts::FRAC_1_SQRT_2,
+];
+
+const CHUNK_JSON: [u8; 4] = *b"JSON";
+const CHUNK_BIN: [u8; 4] = *b"BIN\0";
+const UNSIGNED_BYTE: u32 = 5121;
+const FLOAT: u32 = 5126;
+const ARRAY_BUFFER: u32 = 34962;
+
+/// Exports a model to binary glTF (`.glb`).
+///
+/// Every submodel becomes a mesh skinned to the bone hierarchy in its rest
+/// pose, with textures of the first skin family embedded as PNG. Each
+/// sequence becomes an animation of its first blend; sequences stored in
+/// group files beyond `group_files` (see `seqgroup::sequence_anim_bytes`)
+/// are skipped. Attachments are empty nodes under their bone.
+pub fn model_glb(files: &ModelFiles, group_files: &[&[u8]]) -> ParsingResult<Vec<u8>> {
+    let model = files.model()?;
+    let mut gltf = Gltf::default();
+
+    let rest = rest_pose(model.bones);
+    let rest_matrices = bone_matrices(model.bones, &rest)?;
+
+    // Bone nodes first, so joint and node indices match.
+    let mut children = vec![Vec::new(); model.bones.len()];
+    let mut roots = Vec::new();
+    for (bone_id, bone) in model.bones.iter().enumerate() {
+        match usize::try_from(bone.parent.get()) {
+            Ok(parent) => children
+                .get_mut(parent)
+                .ok_or(ParsingError::OutOfRange("mdl bone parent"))?
+                .push(bone_id),
+            Err(_) => roots.push(bone_id),
+        }
+    }
+    for (attachment_id, attachment) in model.attachments.iter().enumerate() {
+        let bone = usize::try_from(attachment.bone.get())
+            .ok()
+            .filter(|&bone| bone < model.bones.len())
+            .ok_or(ParsingError::OutOfRange("mdl attachment bone"))?;
+        children[bone].push(model.bones.len() + attachment_id);
+    }
+    for ((bone, pose), children) in model.bones.iter().zip(&rest).zip(&children) {
+        gltf.nodes.push(format!(
+            r#"{{"name":{},"translation":{},"rotation":{}{}}}"#,
+            json_name(&bone.name, "bone", gltf.nodes.len()),
+            json_floats(&pose.position),
+            json_floats(&pose.rotation),
+            json_children(children),
+        ));
+    }
+    for (attachment_id, attachment) in model.attachments.iter().enumerate() {
+        gltf.nodes.push(format!(
+            r#"{{"name":{},"translation":{}}}"#,
+            json_name(&attachment.name, "attachment", attachment_id),
+            json_floats(&vec3(&attachment.org)),
+        ));
+    }
+
+    if !model.bones.is_empty() {
+        let inverse_binds: Vec<f32> = rest_matrices.iter().flat_map(inverse_bind).collect();
+        let inverse_binds = gltf.accessor(
+            &floats(inverse_binds),
+            FLOAT,
+            model.bones.len(),
+            "MAT4",
+            None,
+            None,
+        );
+        gltf.skins.push(format!(
+            r#"{{"inverseBindMatrices":{inverse_binds},"joints":{}}}"#,
+            json_indices(0..model.bones.len()),
+        ));
+    }
+
+    for texture in model.textures {
+        gltf.texture(files, texture)?;
+    }
+
+    for (bodypart_id, bodypart) in model.bodyparts.iter().enumerate() {
+        for (submodel_id, submodel) in bodypart_models(&files.model, bodypart)?.iter().enumerate() {
+            if let Some(mesh) = gltf.mesh(&files.model, &model, submodel, &rest_matrices)? {
+                roots.push(gltf.nodes.len());
+                gltf.nodes.push(format!(
+                    r#"{{"name":"{}/{}","mesh":{mesh}{}}}"#,
+                    escape(&lossy_name(&bodypart.name, "bodypart", bodypart_id)),
+                    escape(&lossy_name(&submodel.name, "model", submodel_id)),
+                    if gltf.skins.is_empty() {
+                        ""
+                    } else {
+                        r#","skin":0"#
+                    },
+                ));
+            }
+        }
+    }
+
+    for (sequence_id, sequence) in model.sequences.iter().enumerate() {
+        let group = usize::try_from(sequence.sequence_group.get())
+            .map_err(|_| ParsingError::Invalid("mdl sequence group"))?;
+        if group > group_files.len() {
+            continue;
+        }
+        let bytes = sequence_anim_bytes(&files.model, group_files, sequence)?;
+        let anim = sequence_anim(bytes, model.bones.len(), sequence, 0)?;
+
+        let fps = Some(sequence.fps.get())
+            .filter(|fps| fps.is_finite() && *fps > 0.0)
+            .unwrap_or(1.0);
+        let frames_num = anim.frames_num.max(1);
+        let poses = (0..frames_num)
+            .map(|frame| pose(&anim, model.bones, sequence, frame as f32, &[]))
+            .collect::<ParsingResult<Vec<_>>>()?;
+
+        let times: Vec<f32> = (0..frames_num).map(|frame| frame as f32 / fps).collect();
+        let input = gltf.accessor(
+            &floats(times.iter().copied()),
+            FLOAT,
+            frames_num,
+            "SCALAR",
+            Some((vec![times[0]], vec![times[frames_num - 1]])),
+            None,
+        );
+
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+        for bone_id in 0..model.bones.len() {
+            for (path, values, ty) in [
+                (
+                    "translation",
+                    floats(poses.iter().flat_map(|pose| pose[bone_id].position)),
+                    "VEC3",
+                ),
+                (
+                    "rotation",
+                    floats(poses.iter().flat_map(|pose| pose[bone_id].rotation)),
+                    "VEC4",
+                ),
+            ] {
+                let output = gltf.accessor(&values, FLOAT, frames_num, ty, None, None);
+                channels.push(format!(
+                    r#"{{"sampler":{},"target":{{"node":{bone_id},"path":"{path}"}}}}"#,
+                    samplers.len(),
+                ));
+                samplers.push(format!(r#"{{"input":{input},"output":{output}}}"#));
+            }
+        }
+        gltf.animations.push(format!(
+            r#"{{"name":{},"samplers":[{}],"channels":[{}]}}"#,
+            json_name(&sequence.label, "sequence", sequence_id),
+            samplers.join(","),
+            channels.join(","),
+        ));
+    }
+
+    let root = gltf.nodes.len();
+    gltf.nodes.push(format!(
+        r#"{{"name":{},"rotation":{}{}}}"#,
+        json_name(&model.header.name, "model", 0),
+        json_floats(&Z_UP_ROTATION),
+        json_children(&roots),
+    ));
+
+    Ok(gltf.glb(root))
+}
+
+#[derive(Default)]
+struct Gltf {
+    bin: Vec<u8>,
+    buffer_views: Vec<String>,
+    accessors: Vec<String>,
+    images: Vec<String>,
+    textures: Vec<String>,
+    materials: Vec<String>,
+    meshes: Vec<String>,
+    nodes: Vec<String>,
+    skins: Vec<String>,
+    animations: Vec<String>,
+}
+
+impl Gltf {
+    fn buffer_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+        let mut view = format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{}"#,
+            self.bin.len(),
+            data.len()
+        );
+        if let Some(target) = target {
+            let _ = write!(view, r#","target":{target}"#);
+        }
+        view.push('}');
+        self.bin.extend_from_slice(data);
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    fn accessor(
+        &mut self,
+        data: &[u8],
+        component: u32,
+        count: usize,
+        ty: &str,
+        bounds: Option<(Vec<f32>, Vec<f32>)>,
+        target: Option<u32>,
+    ) -> usize {
+        let view = self.buffer_view(data, target);
+        let mut accessor = format!(
+            r#"{{"bufferView":{view},"componentType":{component},"count":{count},"type":"{ty}""#
+        );
+        if let Some((min, max)) = bounds {
+            let _ = write!(
+                accessor,
+                r#","min":{},"max":{}"#,
+                json_floats(&min),
+                json_floats(&max)
+            );
+        }
+        accessor.push('}');
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn texture(&mut self, files: &ModelFiles, texture: &Texture) -> ParsingResult<()> {
+        let data = files.texture_data(texture)?;
+        let masked = texture.flags.get() & STUDIO_NF_MASKED != 0;
+
+        let mut pixels = Vec::with_capacity(data.indices[0].len() * 4);
+        for &index in data.indices[0] {
+            let [r, g, b] = data.palette[usize::from(index)];
+            pixels.extend_from_slice(&[r, g, b]);
+            if masked {
+                pixels.push(if index == 255 { 0 } else { 255 });
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, texture.width.get(), texture.height.get());
+        encoder.set_color(if masked {
+            png::ColorType::Rgba
+        } else {
+            png::ColorType::Rgb
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|_| ParsingError::Invalid("gltf png texture"))?;
+
+        let view = self.buffer_view(&png, None);
+        let name = json_name(&texture.name, "texture", self.images.len());
+        self.images.push(format!(
+            r#"{{"name":{name},"bufferView":{view},"mimeType":"image/png"}}"#
+        ));
+        self.textures.push(format!(
+            r#"{{"sampler":0,"source":{}}}"#,
+            self.images.len() - 1
+        ));
+
+        let mut material = format!(
+            r#"{{"name":{name},"pbrMetallicRoughness":{{"baseColorTexture":{{"index":{}}},"metallicFactor":0}}"#,
+            self.textures.len() - 1
+        );
+        if masked {
+            material.push_str(r#","alphaMode":"MASK""#);
+        }
+        material.push('}');
+        self.materials.push(material);
+        Ok(())
+    }
+
+    fn mesh(
+        &mut self,
+        bytes: &[u8],
+        model: &SkeletalModel<'_>,
+        submodel: &Model,
+        rest_matrices: &[BoneMatrix],
+    ) -> ParsingResult<Option<usize>> {
+        let positions = skin_vertices(bytes, submodel, rest_matrices)?;
+        let normals = skin_normals(bytes, submodel, rest_matrices)?;
+        let vertex_bones = model_vertex_bones(bytes, submodel)?;
+
+        let mut primitives = Vec::new();
+        for mesh in model_meshes(bytes, submodel)? {
+            let triangles = mesh_triangles(bytes, submodel, mesh)?;
+            if triangles.is_empty() {
+                continue;
+            }
+            let material = if model.textures.is_empty() {
+                None
+            } else {
+                Some(skin_texture(model, 0, mesh.skin_ref.get())?)
+            };
+            let scale = match material {
+                Some(texture_id) => tex_coord_scale(&model.textures[texture_id])?,
+                None => [1.0; 2],
+            };
+
+            let vertices: Vec<_> = triangles.iter().flatten().collect();
+            let mut min = [f32::MAX; 3];
+            let mut max = [f32::MIN; 3];
+            for vertex in &vertices {
+                let position = positions[vertex.vertex_id];
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(position[axis]);
+                    max[axis] = max[axis].max(position[axis]);
+                }
+            }
+
+            let count = vertices.len();
+            let position = self.accessor(
+                &floats(
+                    vertices
+                        .iter()
+                        .flat_map(|vertex| positions[vertex.vertex_id]),
+                ),
+                FLOAT,
+                count,
+                "VEC3",
+                Some((min.to_vec(), max.to_vec())),
+                Some(ARRAY_BUFFER),
+            );
+            let normal = self.accessor(
+                &floats(
+                    vertices
+                        .iter()
+                        .flat_map(|vertex| normalize(normals[vertex.normal_id])),
+                ),
+                FLOAT,
+                count,
+                "VEC3",
+                None,
+                Some(ARRAY_BUFFER),
+            );
+            let tex_coords = self.accessor(
+                &floats(vertices.iter().flat_map(|vertex| {
+                    [
+                        f32::from(vertex.tex_coords[0]) * scale[0],
+                        f32::from(vertex.tex_coords[1]) * scale[1],
+                    ]
+                })),
+                FLOAT,
+                count,
+                "VEC2",
+                None,
+                Some(ARRAY_BUFFER),
+            );
+
+            let mut primitive = format!(
+                r#"{{"attributes":{{"POSITION":{position},"NORMAL":{normal},"TEXCOORD_0":{tex_coords}"#
+            );
+            // Skinning attributes are only valid on meshes of a skinned node.
+            if !self.skins.is_empty() {
+                let joints: Vec<u8> = vertices
+                    .iter()
+                    .flat_map(|vertex| [vertex_bones[vertex.vertex_id], 0, 0, 0])
+                    .collect();
+                let joints = self.accessor(
+                    &joints,
+                    UNSIGNED_BYTE,
+                    count,
+                    "VEC4",
+                    None,
+                    Some(ARRAY_BUFFER),
+                );
+                let weights = self.accessor(
+                    &floats((0..count).flat_map(|_| [1.0, 0.0, 0.0, 0.0])),
+                    FLOAT,
+                    count,
+                    "VEC4",
+                    None,
+                    Some(ARRAY_BUFFER),
+                );
+                let _ = write!(primitive, r#","JOINTS_0":{joints},"WEIGHTS_0":{weights}"#);
+            }
+            primitive.push('}');
+            if let Some(material) = material {
+                let _ = write!(primitive, r#","material":{material}"#);
+            }
+            primitive.push('}');
+            primitives.push(primitive);
+        }
+
+        if primitives.is_empty() {
+            return Ok(None);
+        }
+        self.meshes
+            .push(format!(r#"{{"primitives":[{}]}}"#, primitives.join(",")));
+        Ok(Some(self.meshes.len() - 1))
+    }
+
+    fn glb(mut self, root: usize) -> Vec<u8> {
+        self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+
+        let mut json = format!(
+            r#"{{"asset":{{"version":"2.0","generator":"goldsrc-rs"}},"scene":0,"scenes":[{{"nodes":[{root}]}}],"buffers":[{{"byteLength":{}}}]"#,
+            self.bin.len()
+        );
+        if !self.textures.is_empty() {
+            json.push_str(r#","samplers":[{"magFilter":9729,"minFilter":9987}]"#);
+        }
+        for (name, items) in [
+            ("bufferViews", &self.buffer_views),
+            ("accessors", &self.accessors),
+            ("images", &self.images),
+            ("textures", &self.textures),
+            ("materials", &self.materials),
+            ("meshes", &self.meshes),
+            ("nodes", &self.nodes),
+            ("skins", &self.skins),
+            ("animations", &self.animations),
+        ] {
+            if !items.is_empty() {
+                let _ = write!(json, r#","{name}":[{}]"#, items.join(","));
+            }
+        }
+        json.push('}');
+        while json.len() % 4 != 0 {
+            json.push(' ');
+        }
+
+        let length = 12 + 8 + json.len() + 8 + self.bin.len();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(&GLB_MAGIC);
+        glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_JSON);
+        glb.extend_from_slice(json.as_bytes());
+        glb.extend_from_slice(&(self.bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_BIN);
+        glb.extend_from_slice(&self.bin);
+        glb
+    }
+}
+
+fn rest_pose(bones: &[Bone]) -> Vec<BonePose> {
+    bones
+        .iter()
+        .map(|bone| {
+            let [x, y, z, xr, yr, zr] = bone.value.map(|value| value.get());
+            BonePose {
+                position: [x, y, z],
+                rotation: angle_quaternion([xr, yr, zr]),
+            }
+        })
+        .collect()
+}
+
+/// Column-major inverse of a rigid bone transform.
+fn inverse_bind(matrix: &BoneMatrix) -> [f32; 16] {
+    let translation: [f32; 3] = std::array::from_fn(|i| {
+        -(matrix[0][i] * matrix[0][3] + matrix[1][i] * matrix[1][3] + matrix[2][i] * matrix[2][3])
+    });
+    [
+        matrix[0][0],
+        matrix[0][1],
+        matrix[0][2],
+        0.0,
+        matrix[1][0],
+        matrix[1][1],
+        matrix[1][2],
+        0.0,
+        matrix[2][0],
+        matrix[2][1],
+        matrix[2][2],
+        0.0,
+        translation[0],
+        translation[1],
+        translation[2],
+        1.0,
+    ]
+}
+
+fn floats(values: impl IntoIterator<Item = f32>) -> Vec<u8> {
+    values.into_iter().flat_map(f32::to_le_bytes).collect()
+}
+
+fn json_floats(values: &[f32]) -> String {
+    let values: Vec<_> = values
+        .iter()
+        .map(|value| if value.is_finite() { *value } else { 0.0 }.to_string())
+        .collect();
+    format!("[{}]", values.join(","))
+}
+
+fn json_indices(indices: impl IntoIterator<Item = usize>) -> String {
+    let indices: Vec<_> = indices.into_iter().map(|index| index.to_string()).collect();
+    format!("[{}]", indices.join(","))
+}
+
+fn json_children(children: &[usize]) -> String {
+    if children.is_empty() {
+        String::new()
+    } else {
+        format!(r#","children":{}"#, json_indices(children.iter().copied()))
+    }
+}
+
+fn json_name(name: &[u8], fallback: &str, index: usize) -> String {
+    format!("\"{}\"", escape(&lossy_name(name, fallback, index)))
+}
+
+fn lossy_name(name: &[u8], fallback: &str, index: usize) -> String {
+    let name = cstring_bytes(name);
+    if name.is_empty() {
+        format!("{fallback}{index}")
+    } else {
+        String::from_utf8_lossy(name).into_owned()
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", u32::from(c));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
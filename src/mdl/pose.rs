@@ -0,0 +1,210 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{
+    error::{ParsingError, ParsingResult},
+    math::lerp,
+    mdl::{
+        Bone, STUDIO_X, STUDIO_Y, STUDIO_Z, SequenceDesc,
+        anim::{ANIM_CHANNELS, SequenceAnim},
+    },
+};
+
+/// Quaternion as `[x, y, z, w]`.
+pub type Quaternion = [f32; 4];
+/// Row-major 3x4 transform, the last column holding the translation.
+pub type BoneMatrix = [[f32; 4]; 3];
+
+/// Local transform of a bone relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BonePose {
+    /// Position.
+    pub position: [f32; 3],
+    /// Rotation.
+    pub rotation: Quaternion,
+}
+
+/// Local bone transforms of a sequence at a frame (`StudioCalcRotations`).
+///
+/// `frame` is clamped to the sequence, fractional frames interpolating
+/// towards the next one. Positions along the sequence motion axes are
+/// zeroed on the motion bone, the movement being applied to the entity.
+///
+/// `adjustments` are the bone controller adjustments (see
+/// `controller::bone_adjustments`), missing ones counting as zero.
+pub fn pose(
+    anim: &SequenceAnim<'_>,
+    bones: &[Bone],
+    sequence: &SequenceDesc,
+    frame: f32,
+    adjustments: &[f32],
+) -> ParsingResult<Vec<BonePose>> {
+    if bones.len() != anim.bones_num {
+        return Err(ParsingError::Invalid("mdl pose bones"));
+    }
+
+    let last = anim.frames_num.saturating_sub(1);
+    let frame = frame.clamp(0.0, last as f32);
+    let index = frame as usize;
+    let s = frame - index as f32;
+    let next = (index + 1).min(last);
+
+    let mut poses = bones
+        .iter()
+        .enumerate()
+        .map(|(bone_id, bone)| {
+            let adjust = |mut values: [f32; ANIM_CHANNELS]| {
+                for (value, controller) in values.iter_mut().zip(&bone.bone_controller) {
+                    if let Some(adjustment) = usize::try_from(controller.get())
+                        .ok()
+                        .and_then(|id| adjustments.get(id))
+                    {
+                        *value += adjustment;
+                    }
+                }
+                values
+            };
+            let [x1, y1, z1, xr1, yr1, zr1] = adjust(anim.values(bone, bone_id, index)?);
+            let [x2, y2, z2, xr2, yr2, zr2] = adjust(anim.values(bone, bone_id, next)?);
+
+            let from = angle_quaternion([xr1, yr1, zr1]);
+            let rotation = if [xr1, yr1, zr1] == [xr2, yr2, zr2] {
+                from
+            } else {
+                quaternion_slerp(from, angle_quaternion([xr2, yr2, zr2]), s)
+            };
+
+            Ok(BonePose {
+                position: lerp([x1, y1, z1], [x2, y2, z2], s),
+                rotation,
+            })
+        })
+        .collect::<ParsingResult<Vec<_>>>()?;
+
+    let motion_type = sequence.motion_type.get();
+    if let Some(motion) = usize::try_from(sequence.motion_bone.get())
+        .ok()
+        .and_then(|bone_id| poses.get_mut(bone_id))
+    {
+        for (axis, flag) in [STUDIO_X, STUDIO_Y, STUDIO_Z].into_iter().enumerate() {
+            if motion_type & flag != 0 {
+                motion.position[axis] = 0.0;
+            }
+        }
+    }
+
+    Ok(poses)
+}
+
+/// Bone-to-model transforms of a pose, concatenated through the bone parents.
+///
+/// Parents must come before their children, as the engine expects.
+pub fn bone_matrices(bones: &[Bone], pose: &[BonePose]) -> ParsingResult<Vec<BoneMatrix>> {
+    if bones.len() != pose.len() {
+        return Err(ParsingError::Invalid("mdl pose bones"));
+    }
+
+    let mut matrices: Vec<BoneMatrix> = Vec::with_capacity(bones.len());
+    for (bone, pose) in bones.iter().zip(pose) {
+        let mut local = quaternion_matrix(pose.rotation);
+        for (row, &position) in local.iter_mut().zip(&pose.position) {
+            row[3] = position;
+        }
+
+        let matrix = match usize::try_from(bone.parent.get()) {
+            Ok(parent) => concat_transforms(
+                matrices
+                    .get(parent)
+                    .ok_or(ParsingError::OutOfRange("mdl bone parent"))?,
+                &local,
+            ),
+            Err(_) => local,
+        };
+        matrices.push(matrix);
+    }
+
+    Ok(matrices)
+}
+
+/// Quaternion of euler angles in radians, as `[roll, pitch, yaw]` around
+/// the X, Y and Z axes (`AngleQuaternion`).
+pub fn angle_quaternion(angles: [f32; 3]) -> Quaternion {
+    let (sr, cr) = (angles[0] * 0.5).sin_cos();
+    let (sp, cp) = (angles[1] * 0.5).sin_cos();
+    let (sy, cy) = (angles[2] * 0.5).sin_cos();
+
+    [
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+        cr * cp * cy + sr * sp * sy,
+    ]
+}
+
+/// Spherical interpolation between two quaternions along the shortest arc
+/// (`QuaternionSlerp`).
+pub fn quaternion_slerp(p: Quaternion, q: Quaternion, t: f32) -> Quaternion {
+    let mut a = 0.0;
+    let mut b = 0.0;
+    for i in 0..4 {
+        a += (p[i] - q[i]) * (p[i] - q[i]);
+        b += (p[i] + q[i]) * (p[i] + q[i]);
+    }
+    let q = if a > b { q.map(|v| -v) } else { q };
+
+    let cosom = p[0] * q[0] + p[1] * q[1] + p[2] * q[2] + p[3] * q[3];
+    if 1.0 + cosom > 1e-6 {
+        let (sclp, sclq) = if 1.0 - cosom > 1e-6 {
+            let omega = cosom.acos();
+            let sinom = omega.sin();
+            (((1.0 - t) * omega).sin() / sinom, (t * omega).sin() / sinom)
+        } else {
+            (1.0 - t, t)
+        };
+        std::array::from_fn(|i| sclp * p[i] + sclq * q[i])
+    } else {
+        let qt = [-p[1], p[0], -p[3], p[2]];
+        let sclp = ((1.0 - t) * FRAC_PI_2).sin();
+        let sclq = (t * FRAC_PI_2).sin();
+        [
+            sclp * p[0] + sclq * qt[0],
+            sclp * p[1] + sclq * qt[1],
+            sclp * p[2] + sclq * qt[2],
+            qt[3],
+        ]
+    }
+}
+
+/// Rotation matrix of a quaternion, with a zero translation (`QuaternionMatrix`).
+pub fn quaternion_matrix(q: Quaternion) -> BoneMatrix {
+    let [x, y, z, w] = q;
+    [
+        [
+            1.0 - 2.0 * y * y - 2.0 * z * z,
+            2.0 * x * y - 2.0 * w * z,
+            2.0 * x * z + 2.0 * w * y,
+            0.0,
+        ],
+        [
+            2.0 * x * y + 2.0 * w * z,
+            1.0 - 2.0 * x * x - 2.0 * z * z,
+            2.0 * y * z - 2.0 * w * x,
+            0.0,
+        ],
+        [
+            2.0 * x * z - 2.0 * w * y,
+            2.0 * y * z + 2.0 * w * x,
+            1.0 - 2.0 * x * x - 2.0 * y * y,
+            0.0,
+        ],
+    ]
+}
+
+/// Applies `b` then `a` (`R_ConcatTransforms`).
+pub fn concat_transforms(a: &BoneMatrix, b: &BoneMatrix) -> BoneMatrix {
+    std::array::from_fn(|i| {
+        let mut row: [f32; 4] =
+            std::array::from_fn(|j| a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j]);
+        row[3] += a[i][3];
+        row
+    })
+}
@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+
+use static_assertions::assert_eq_size;
+use zerocopy::{FromBytes, little_endian::U32};
+use zerocopy_derive::*;
+
+use crate::{
+    error::{ParsingError, ParsingResult},
+    mdl::{MDL_VERSION, SequenceDesc},
+};
+
+/// Sequence group file magic.
+pub const SEQUENCE_GROUP_MAGIC: [u8; 4] = *b"IDSQ";
+
+/// Sequence group file header (studioseqhdr_t).
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct SequenceGroupHeader {
+    /// File magic ("IDSQ").
+    pub magic: [u8; 4],
+    /// File version.
+    pub version: U32,
+    /// Group name (C string, not guaranteed UTF-8).
+    pub name: [u8; 64],
+    /// File length in bytes.
+    pub length: U32,
+}
+assert_eq_size!(SequenceGroupHeader, [u8; 76]);
+
+/// Parses the header of a sequence group file (`modelname01.mdl`, ...).
+pub fn sequence_group(bytes: &[u8]) -> ParsingResult<&SequenceGroupHeader> {
+    let (header, _) = SequenceGroupHeader::ref_from_prefix(bytes)
+        .map_err(|_| ParsingError::OutOfRange("mdl sequence group header"))?;
+
+    if header.magic != SEQUENCE_GROUP_MAGIC {
+        return Err(ParsingError::WrongFourCC {
+            got: header.magic,
+            expected: SEQUENCE_GROUP_MAGIC,
+        });
+    }
+
+    let version = header.version.get();
+    if version != MDL_VERSION {
+        return Err(ParsingError::WrongVersion {
+            got: version,
+            expected: MDL_VERSION,
+        });
+    }
+
+    Ok(header)
+}
+
+/// Path of a sequence group file next to its model, `group` starting at 1.
+pub fn sequence_group_path(model_path: &Path, group: usize) -> PathBuf {
+    let mut name = model_path.file_stem().unwrap_or_default().to_owned();
+    name.push(format!("{group:02}.mdl"));
+    model_path.with_file_name(name)
+}
+
+/// Bytes holding the animations of a sequence, to pass to `anim::sequence_anim`.
+///
+/// Sequence group 0 lives in the model itself, others in `group_files`
+/// which holds the group files in order, starting with group 1.
+pub fn sequence_anim_bytes<'a>(
+    model_bytes: &'a [u8],
+    group_files: &[&'a [u8]],
+    sequence: &SequenceDesc,
+) -> ParsingResult<&'a [u8]> {
+    let group = usize::try_from(sequence.sequence_group.get())
+        .map_err(|_| ParsingError::Invalid("mdl sequence group"))?;
+    if group == 0 {
+        return Ok(model_bytes);
+    }
+
+    let bytes = group_files
+        .get(group - 1)
+        .ok_or(ParsingError::OutOfRange("mdl sequence group"))?;
+    sequence_group(bytes)?;
+    Ok(bytes)
+}
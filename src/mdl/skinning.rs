@@ -0,0 +1,159 @@
+use crate::{
+    common::BBox,
+    error::{ParsingError, ParsingResult},
+    math::{dot, vec3},
+    mdl::{
+        Model, SkeletalModel, Texture,
+        body::{body_models, skin_texture},
+        bodypart_models, model_meshes, model_normal_bones, model_normals, model_vertex_bones,
+        model_vertices,
+        pose::BoneMatrix,
+        tricmd::mesh_triangles,
+    },
+};
+
+/// Vertex of a skinned mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkinnedVertex {
+    /// Position in model space.
+    pub position: [f32; 3],
+    /// Normal in model space.
+    pub normal: [f32; 3],
+    /// Texture coordinates normalized by the texture size.
+    pub tex_coords: [f32; 2],
+}
+
+/// Skinned mesh as a triangle list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkinnedMesh {
+    /// Index into `SkeletalModel::textures`.
+    pub texture_id: usize,
+    /// Vertices, three per triangle.
+    pub vertices: Vec<SkinnedVertex>,
+}
+
+/// Vertices of a model transformed by their bones (`StudioSetupModel` / `VectorTransform`).
+pub fn skin_vertices(
+    bytes: &[u8],
+    model: &Model,
+    matrices: &[BoneMatrix],
+) -> ParsingResult<Vec<[f32; 3]>> {
+    let vertices = model_vertices(bytes, model)?;
+    let bones = model_vertex_bones(bytes, model)?;
+    vertices
+        .iter()
+        .zip(bones)
+        .map(|(vertex, &bone)| {
+            let matrix = bone_matrix(matrices, bone)?;
+            let v = vec3(vertex);
+            Ok(matrix.map(|row| dot(v, [row[0], row[1], row[2]]) + row[3]))
+        })
+        .collect()
+}
+
+/// Normals of a model rotated by their bones (`VectorRotate`).
+pub fn skin_normals(
+    bytes: &[u8],
+    model: &Model,
+    matrices: &[BoneMatrix],
+) -> ParsingResult<Vec<[f32; 3]>> {
+    let normals = model_normals(bytes, model)?;
+    let bones = model_normal_bones(bytes, model)?;
+    normals
+        .iter()
+        .zip(bones)
+        .map(|(normal, &bone)| {
+            let matrix = bone_matrix(matrices, bone)?;
+            let n = vec3(normal);
+            Ok(matrix.map(|row| dot(n, [row[0], row[1], row[2]])))
+        })
+        .collect()
+}
+
+/// Skinned meshes of a submodel with textures from skin family `skin`.
+pub fn skinned_meshes(
+    bytes: &[u8],
+    skeletal: &SkeletalModel<'_>,
+    model: &Model,
+    matrices: &[BoneMatrix],
+    skin: usize,
+) -> ParsingResult<Vec<SkinnedMesh>> {
+    let positions = skin_vertices(bytes, model, matrices)?;
+    let normals = skin_normals(bytes, model, matrices)?;
+
+    model_meshes(bytes, model)?
+        .iter()
+        .map(|mesh| {
+            let texture_id = skin_texture(skeletal, skin, mesh.skin_ref.get())?;
+            let scale = tex_coord_scale(&skeletal.textures[texture_id])?;
+
+            let vertices = mesh_triangles(bytes, model, mesh)?
+                .iter()
+                .flatten()
+                .map(|vertex| SkinnedVertex {
+                    position: positions[vertex.vertex_id],
+                    normal: normals[vertex.normal_id],
+                    tex_coords: [
+                        f32::from(vertex.tex_coords[0]) * scale[0],
+                        f32::from(vertex.tex_coords[1]) * scale[1],
+                    ],
+                })
+                .collect();
+
+            Ok(SkinnedMesh {
+                texture_id,
+                vertices,
+            })
+        })
+        .collect()
+}
+
+/// Skinned meshes of every body part for a `body` value and skin family.
+pub fn skinned_body(
+    bytes: &[u8],
+    skeletal: &SkeletalModel<'_>,
+    matrices: &[BoneMatrix],
+    body: u32,
+    skin: usize,
+) -> ParsingResult<Vec<SkinnedMesh>> {
+    let mut meshes = Vec::new();
+    let models = body_models(skeletal.bodyparts, body)?;
+    for (bodypart, model_id) in skeletal.bodyparts.iter().zip(models) {
+        let model = bodypart_models(bytes, bodypart)?
+            .get(model_id)
+            .ok_or(ParsingError::OutOfRange("mdl body model"))?;
+        meshes.extend(skinned_meshes(bytes, skeletal, model, matrices, skin)?);
+    }
+    Ok(meshes)
+}
+
+/// Bounds of skinned meshes, `None` if they have no vertices.
+pub fn skinned_bbox(meshes: &[SkinnedMesh]) -> Option<BBox<[f32; 3]>> {
+    let mut vertices = meshes.iter().flat_map(|mesh| &mesh.vertices);
+    let first = vertices.next()?.position;
+    Some(vertices.fold(
+        BBox {
+            min: first,
+            max: first,
+        },
+        |bbox, vertex| BBox {
+            min: std::array::from_fn(|i| bbox.min[i].min(vertex.position[i])),
+            max: std::array::from_fn(|i| bbox.max[i].max(vertex.position[i])),
+        },
+    ))
+}
+
+/// Factors normalizing texel coordinates by the size of a texture.
+pub(crate) fn tex_coord_scale(texture: &Texture) -> ParsingResult<[f32; 2]> {
+    let size = [texture.width.get(), texture.height.get()];
+    if size.contains(&0) {
+        return Err(ParsingError::Invalid("mdl texture size"));
+    }
+    Ok(size.map(|size| 1.0 / size as f32))
+}
+
+fn bone_matrix(matrices: &[BoneMatrix], bone: u8) -> ParsingResult<&BoneMatrix> {
+    matrices
+        .get(usize::from(bone))
+        .ok_or(ParsingError::OutOfRange("mdl vertex bone"))
+}
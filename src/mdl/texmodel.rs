@@ -0,0 +1,75 @@
+use crate::{
+    error::{ParsingError, ParsingResult},
+    mdl::{SkeletalModel, Texture, mdl, texture_data},
+    texture::ColorData,
+};
+
+/// Contents of a model file and of its texture model, if any.
+#[derive(Debug, Clone)]
+pub struct ModelFiles {
+    /// Model file.
+    pub model: Vec<u8>,
+    /// Texture model file (`modelnameT.mdl`), loaded when the model has no textures.
+    pub textures: Option<Vec<u8>>,
+}
+
+impl ModelFiles {
+    /// Loads the model at `path` and, if it has no textures, its texture model.
+    ///
+    /// `load` returns the contents of a file, `None` if it doesn't exist.
+    pub fn load(path: &str, mut load: impl FnMut(&str) -> Option<Vec<u8>>) -> ParsingResult<Self> {
+        let model = load(path).ok_or(ParsingError::OutOfRange("mdl model file"))?;
+        let textures = if mdl(&model)?.header.textures.count.get() == 0 {
+            let textures = load(&texture_model_path(path))
+                .ok_or(ParsingError::OutOfRange("mdl texture model file"))?;
+            mdl(&textures)?;
+            Some(textures)
+        } else {
+            None
+        };
+
+        Ok(Self { model, textures })
+    }
+
+    /// Model with its textures and skins taken from the texture model, if any.
+    pub fn model(&self) -> ParsingResult<SkeletalModel<'_>> {
+        let model = mdl(&self.model)?;
+        Ok(match &self.textures {
+            Some(textures) => with_textures(model, mdl(textures)?),
+            None => model,
+        })
+    }
+
+    /// Bytes holding the texture data, to pass to `mdl::texture_data`.
+    pub fn texture_bytes(&self) -> &[u8] {
+        self.textures.as_deref().unwrap_or(&self.model)
+    }
+
+    /// Pixels of a texture of the merged model.
+    pub fn texture_data(&self, texture: &Texture) -> ParsingResult<ColorData<'_, 1>> {
+        texture_data(self.texture_bytes(), texture)
+    }
+}
+
+/// Path of the texture model of a model (`models/scientistT.mdl`).
+pub fn texture_model_path(path: &str) -> String {
+    let stem = match path.len().checked_sub(4) {
+        Some(end) if path.is_char_boundary(end) && path[end..].eq_ignore_ascii_case(".mdl") => {
+            &path[..end]
+        }
+        _ => path,
+    };
+    format!("{stem}T.mdl")
+}
+
+/// Replaces the textures and skins of a model with those of its texture model.
+pub fn with_textures<'a>(
+    model: SkeletalModel<'a>,
+    texture_model: SkeletalModel<'a>,
+) -> SkeletalModel<'a> {
+    SkeletalModel {
+        textures: texture_model.textures,
+        skins: texture_model.skins,
+        ..model
+    }
+}
@@ -0,0 +1,80 @@
+use zerocopy::{FromBytes, little_endian::I16};
+
+use crate::{
+    error::{ParsingError, ParsingResult},
+    mdl::{Mesh, Model},
+};
+
+/// Vertex of a decoded triangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriangleVertex {
+    /// Index into `model_vertices`.
+    pub vertex_id: usize,
+    /// Index into `model_normals`.
+    pub normal_id: usize,
+    /// Texture coordinates in texels.
+    pub tex_coords: [i16; 2],
+}
+
+/// Triangle in OpenGL winding order (strips alternate, fans pivot on the first vertex).
+pub type Triangle = [TriangleVertex; 3];
+
+/// Decodes the triangle command stream of a mesh.
+///
+/// The stream is a list of commands, each starting with a vertex count
+/// (positive for a strip, negative for a fan, zero ending the list) followed
+/// by `vertex, normal, s, t` shorts per vertex.
+pub fn mesh_triangles(bytes: &[u8], model: &Model, mesh: &Mesh) -> ParsingResult<Vec<Triangle>> {
+    let offset = usize::try_from(mesh.tris.offset.get())
+        .map_err(|_| ParsingError::NumberOverflow("mdl tricmds offset"))?;
+    let mut bytes = bytes
+        .get(offset..)
+        .ok_or(ParsingError::OutOfRange("mdl tricmds"))?;
+
+    let verts_num = model.verts_num.get() as usize;
+    let norms_num = model.norms_num.get() as usize;
+    let mut triangles = Vec::with_capacity((mesh.tris.count.get() as usize).min(bytes.len()));
+
+    loop {
+        let (count, rest) =
+            I16::read_from_prefix(bytes).map_err(|_| ParsingError::OutOfRange("mdl tricmd"))?;
+        let count = count.get();
+        if count == 0 {
+            break;
+        }
+
+        let (commands, rest) =
+            <[[I16; 4]]>::ref_from_prefix_with_elems(rest, usize::from(count.unsigned_abs()))
+                .map_err(|_| ParsingError::OutOfRange("mdl tricmd vertices"))?;
+        bytes = rest;
+
+        let mut vertices = Vec::with_capacity(commands.len());
+        for [vertex_id, normal_id, s, t] in commands {
+            let vertex_id = usize::try_from(vertex_id.get())
+                .ok()
+                .filter(|&id| id < verts_num)
+                .ok_or(ParsingError::OutOfRange("mdl tricmd vertex"))?;
+            let normal_id = usize::try_from(normal_id.get())
+                .ok()
+                .filter(|&id| id < norms_num)
+                .ok_or(ParsingError::OutOfRange("mdl tricmd normal"))?;
+            vertices.push(TriangleVertex {
+                vertex_id,
+                normal_id,
+                tex_coords: [s.get(), t.get()],
+            });
+        }
+
+        for i in 2..vertices.len() {
+            triangles.push(if count < 0 {
+                [vertices[0], vertices[i - 1], vertices[i]]
+            } else if i % 2 == 0 {
+                [vertices[i - 2], vertices[i - 1], vertices[i]]
+            } else {
+                [vertices[i - 1], vertices[i - 2], vertices[i]]
+            });
+        }
+    }
+
+    Ok(triangles)
+}
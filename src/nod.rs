@@ -0,0 +1,252 @@
+use static_assertions::assert_eq_size;
+use zerocopy::{
+    FromBytes, Immutable,
+    little_endian::{F32, I16, I32, U32},
+};
+use zerocopy_derive::*;
+
+use crate::{
+    bsp::{Level, vis::point_leaf},
+    common::Vec3f,
+    error::{ParsingError, ParsingResult},
+};
+
+/// Node graph version (Half-Life 1).
+pub const GRAPH_VERSION: u32 = 16;
+/// Number of hull sizes nodes keep routes for.
+pub const MAX_NODE_HULLS: usize = 4;
+/// Number of ranges in the nearest node lookup tables.
+pub const NUM_RANGES: usize = 256;
+/// Number of entries in the nearest node cache.
+pub const CACHE_SIZE: usize = 128;
+
+/// Node info flag values.
+pub const NODE_LAND: u32 = 1 << 0;
+pub const NODE_AIR: u32 = 1 << 1;
+pub const NODE_WATER: u32 = 1 << 2;
+
+/// Link info flag values.
+pub const LINK_SMALL_HULL: u32 = 1 << 0;
+pub const LINK_HUMAN_HULL: u32 = 1 << 1;
+pub const LINK_LARGE_HULL: u32 = 1 << 2;
+pub const LINK_FLY_HULL: u32 = 1 << 3;
+pub const LINK_DISABLED: u32 = 1 << 4;
+
+/// Complete node graph loaded from a NOD file.
+pub struct NodeGraph<'a> {
+    /// Graph header (saved `CGraph`).
+    pub header: &'a GraphHeader,
+    /// Nodes.
+    pub nodes: &'a [GraphNode],
+    /// Link pool, nodes reference contiguous ranges of it.
+    pub links: &'a [GraphLink],
+    /// Nearest node lookup table.
+    pub dist_info: &'a [DistInfo],
+    /// Compressed routing table.
+    pub route_info: &'a [u8],
+    /// Link hash table.
+    pub hash_links: &'a [I16],
+}
+
+/// Saved `CGraph` object (32-bit layout, pointers are meaningless).
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct GraphHeader {
+    /// Whether the graph is in memory.
+    pub graph_present: U32,
+    /// Whether entity pointers are set.
+    pub graph_pointers_set: U32,
+    /// Whether optimal routes are computed.
+    pub routing_complete: U32,
+    /// Nodes pointer.
+    pub nodes_ptr: U32,
+    /// Link pool pointer.
+    pub links_ptr: U32,
+    /// Routing table pointer.
+    pub route_info_ptr: U32,
+    /// Number of nodes.
+    pub nodes_num: I32,
+    /// Number of links.
+    pub links_num: I32,
+    /// Routing table size in bytes.
+    pub route_info_size: I32,
+    /// Distance info pointer.
+    pub dist_info_ptr: U32,
+    /// First sorted node of each range, per axis.
+    pub range_start: [[I32; NUM_RANGES]; 3],
+    /// Last sorted node of each range, per axis.
+    pub range_end: [[I32; NUM_RANGES]; 3],
+    /// Shortest distance of the last nearest node search.
+    pub shortest: F32,
+    /// Result of the last nearest node search.
+    pub nearest: I32,
+    /// Search bounds.
+    pub min: [I32; 3],
+    /// Search bounds.
+    pub max: [I32; 3],
+    /// Search box bounds.
+    pub min_box: [I32; 3],
+    /// Search box bounds.
+    pub max_box: [I32; 3],
+    /// Search counter.
+    pub checked_counter: I32,
+    /// Minimum node coordinates.
+    pub region_min: Vec3f,
+    /// Maximum node coordinates.
+    pub region_max: Vec3f,
+    /// Nearest node cache.
+    pub cache: [CacheEntry; CACHE_SIZE],
+    /// Primes used by the link hash.
+    pub hash_primes: [I32; 16],
+    /// Hash links pointer.
+    pub hash_links_ptr: U32,
+    /// Number of hash links.
+    pub hash_links_num: I32,
+    /// Last active idle search node.
+    pub last_active_idle_search: I32,
+    /// Last cover search node.
+    pub last_cover_search: I32,
+}
+
+/// Nearest node cache entry.
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct CacheEntry {
+    /// Looked up position.
+    pub origin: Vec3f,
+    /// Nearest node or -1.
+    pub node: I16,
+    /// Padding.
+    pub pad: I16,
+}
+
+/// Node (`CNode`).
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct GraphNode {
+    /// Node position.
+    pub origin: Vec3f,
+    /// Position used for visibility checks (land nodes are raised).
+    pub origin_peek: Vec3f,
+    /// Region of each coordinate.
+    pub region: [u8; 3],
+    /// Padding.
+    pub pad: u8,
+    /// Node info flags.
+    pub info: U32,
+    /// Number of links.
+    pub links_num: I32,
+    /// Index of the first link in the link pool.
+    pub first_link: I32,
+    /// Routing table offsets per hull, without and with door capability.
+    pub next_best_node: [[I32; 2]; MAX_NODE_HULLS],
+    /// Path search scratch value.
+    pub closest_so_far: F32,
+    /// Path search scratch value.
+    pub previous_node: I32,
+    /// Hint type.
+    pub hint_type: I16,
+    /// Hint activity.
+    pub hint_activity: I16,
+    /// Yaw to face the hint.
+    pub hint_yaw: F32,
+}
+
+/// Link between two nodes (`CLink`).
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct GraphLink {
+    /// Node owning the link.
+    pub src_node: I32,
+    /// Node at the other end of the link.
+    pub dest_node: I32,
+    /// Blocking entity pointer.
+    pub link_ent_ptr: U32,
+    /// Blocking brush model name, e.g. `*12` (not NUL terminated).
+    pub link_ent_model: [u8; 4],
+    /// Link info flags.
+    pub info: U32,
+    /// Link length.
+    pub weight: F32,
+}
+
+/// Nearest node lookup entry (`DIST_INFO`).
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct DistInfo {
+    /// Nodes sorted by each axis.
+    pub sorted_by: [I32; 3],
+    /// Search counter.
+    pub checked_event: I32,
+}
+
+/// Parses a `.nod` node graph.
+pub fn node_graph(bytes: &[u8]) -> ParsingResult<NodeGraph<'_>> {
+    let (version, bytes) =
+        U32::ref_from_prefix(bytes).map_err(|_| ParsingError::OutOfRange("nod version"))?;
+    let version = version.get();
+    if version != GRAPH_VERSION {
+        return Err(ParsingError::WrongVersion {
+            got: version,
+            expected: GRAPH_VERSION,
+        });
+    }
+
+    let (header, bytes) =
+        GraphHeader::ref_from_prefix(bytes).map_err(|_| ParsingError::OutOfRange("nod header"))?;
+
+    let (nodes, bytes) = slice_prefix(bytes, header.nodes_num.get(), "nod nodes")?;
+    let (links, bytes) = slice_prefix(bytes, header.links_num.get(), "nod links")?;
+    let (dist_info, bytes) = slice_prefix(bytes, header.nodes_num.get(), "nod dist info")?;
+    let (route_info, bytes) = slice_prefix(bytes, header.route_info_size.get(), "nod route info")?;
+    let (hash_links, _) = slice_prefix(bytes, header.hash_links_num.get(), "nod hash links")?;
+
+    Ok(NodeGraph {
+        header,
+        nodes,
+        links,
+        dist_info,
+        route_info,
+        hash_links,
+    })
+}
+
+/// Links of a node.
+pub fn node_links<'a>(graph: &NodeGraph<'a>, node: &GraphNode) -> ParsingResult<&'a [GraphLink]> {
+    let first = usize::try_from(node.first_link.get())
+        .map_err(|_| ParsingError::OutOfRange("nod node links"))?;
+    let count = usize::try_from(node.links_num.get())
+        .map_err(|_| ParsingError::OutOfRange("nod node links"))?;
+
+    graph
+        .links
+        .get(first..first + count)
+        .ok_or(ParsingError::OutOfRange("nod node links"))
+}
+
+/// World leaf containing each node (using `origin_peek`, which lies above the floor).
+pub fn node_leaves(level: &Level<'_>, graph: &NodeGraph<'_>) -> ParsingResult<Vec<usize>> {
+    graph
+        .nodes
+        .iter()
+        .map(|node| point_leaf(level, node.origin_peek.map(|v| v.get())))
+        .collect()
+}
+
+fn slice_prefix<'a, T>(
+    bytes: &'a [u8],
+    count: i32,
+    label: &'static str,
+) -> ParsingResult<(&'a [T], &'a [u8])>
+where
+    T: Immutable + FromBytes,
+{
+    let count = usize::try_from(count).map_err(|_| ParsingError::NumberOverflow(label))?;
+    <[T]>::ref_from_prefix_with_elems(bytes, count).map_err(|_| ParsingError::OutOfRange(label))
+}
+
+assert_eq_size!(GraphHeader, [u8; 8396]);
+assert_eq_size!(CacheEntry, [u8; 16]);
+assert_eq_size!(GraphNode, [u8; 88]);
+assert_eq_size!(GraphLink, [u8; 24]);
+assert_eq_size!(DistInfo, [u8; 16]);
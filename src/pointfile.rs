@@ -0,0 +1,45 @@
+use crate::{
+    bsp::{Level, vis::point_leaf},
+    error::{ParsingError, ParsingResult},
+    util::parse_f32,
+};
+
+/// Parses a leak pointfile (`.pts` or `.lin`) into a polyline.
+///
+/// Lines hold either a point (`x y z`) or a segment (`x y z - x y z`);
+/// segments are chained, dropping points repeated between them.
+pub fn pointfile(bytes: &[u8]) -> ParsingResult<Vec<[f32; 3]>> {
+    let mut points: Vec<[f32; 3]> = Vec::new();
+
+    for line in bytes.split(|&b| b == b'\n') {
+        let mut values = Vec::with_capacity(6);
+        for token in line
+            .split(|&b| b.is_ascii_whitespace() || b == b'(' || b == b')')
+            .filter(|token| !token.is_empty() && *token != b"-")
+        {
+            values.push(parse_f32(token).ok_or(ParsingError::Invalid("pointfile coordinate"))?);
+        }
+
+        let line_points = match values.as_slice() {
+            [] => continue,
+            [x, y, z] => vec![[*x, *y, *z]],
+            [x0, y0, z0, x1, y1, z1] => vec![[*x0, *y0, *z0], [*x1, *y1, *z1]],
+            _ => return Err(ParsingError::Invalid("pointfile line")),
+        };
+        for point in line_points {
+            if points.last() != Some(&point) {
+                points.push(point);
+            }
+        }
+    }
+
+    Ok(points)
+}
+
+/// World leaf of every point of a leak trail.
+pub fn trail_leaves(level: &Level<'_>, points: &[[f32; 3]]) -> ParsingResult<Vec<usize>> {
+    points
+        .iter()
+        .map(|&point| point_leaf(level, point))
+        .collect()
+}
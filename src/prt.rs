@@ -0,0 +1,115 @@
+use crate::{
+    bsp::{Level, vis::vis_leaves_num},
+    error::{ParsingError, ParsingResult},
+    util::{parse_f32, parse_usize},
+};
+
+/// Portal file magic (Quake 1 / GoldSrc).
+pub const PRT_MAGIC: [u8; 4] = *b"PRT1";
+
+/// Portal file written by the BSP compiler for the VIS stage.
+#[derive(Debug, Clone)]
+pub struct PortalFile {
+    /// Number of vis leaves (the solid leaf 0 is not counted).
+    pub leaves_num: usize,
+    /// Portals between leaves.
+    pub portals: Vec<Portal>,
+}
+
+/// Portal between two vis leaves.
+#[derive(Debug, Clone)]
+pub struct Portal {
+    /// Vis leaf indices on both sides of the portal.
+    pub leaves: [usize; 2],
+    /// Portal polygon.
+    pub winding: Vec<[f32; 3]>,
+}
+
+impl Portal {
+    /// Indices into `Level::leaves` on both sides of the portal.
+    ///
+    /// Vis leaves start after the solid leaf 0.
+    pub fn level_leaves(&self) -> [usize; 2] {
+        self.leaves.map(|leaf| leaf + 1)
+    }
+}
+
+impl PortalFile {
+    /// Checks that the portal file was written for the level.
+    pub fn validate(&self, level: &Level<'_>) -> ParsingResult<()> {
+        if self.leaves_num != vis_leaves_num(level) {
+            return Err(ParsingError::Invalid("prt leaves count"));
+        }
+        let leaves_num = level.leaves.len();
+        if self
+            .portals
+            .iter()
+            .flat_map(Portal::level_leaves)
+            .any(|leaf| leaf >= leaves_num)
+        {
+            return Err(ParsingError::OutOfRange("prt portal leaf"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a `.prt` portal file.
+pub fn portal_file(bytes: &[u8]) -> ParsingResult<PortalFile> {
+    let mut tokens = bytes
+        .split(|&b| b.is_ascii_whitespace() || b == b'(' || b == b')')
+        .filter(|token| !token.is_empty());
+
+    let magic = tokens
+        .next()
+        .ok_or(ParsingError::OutOfRange("prt header"))?;
+    if magic != PRT_MAGIC {
+        let mut got = [0; 4];
+        let len = magic.len().min(4);
+        got[..len].copy_from_slice(&magic[..len]);
+        return Err(ParsingError::WrongFourCC {
+            got,
+            expected: PRT_MAGIC,
+        });
+    }
+
+    let leaves_num = next_token(&mut tokens, parse_usize, "prt leaves count")?;
+    let portals_num = next_token(&mut tokens, parse_usize, "prt portals count")?;
+
+    let mut portals = Vec::with_capacity(portals_num.min(bytes.len()));
+    for _ in 0..portals_num {
+        let points_num = next_token(&mut tokens, parse_usize, "prt portal points count")?;
+        let leaves = [
+            next_token(&mut tokens, parse_usize, "prt portal leaf")?,
+            next_token(&mut tokens, parse_usize, "prt portal leaf")?,
+        ];
+        if leaves.iter().any(|&leaf| leaf >= leaves_num) {
+            return Err(ParsingError::OutOfRange("prt portal leaf"));
+        }
+
+        let mut winding = Vec::with_capacity(points_num.min(bytes.len()));
+        for _ in 0..points_num {
+            let mut point = [0.0; 3];
+            for slot in &mut point {
+                *slot = next_token(&mut tokens, parse_f32, "prt portal point")?;
+            }
+            winding.push(point);
+        }
+
+        portals.push(Portal { leaves, winding });
+    }
+
+    Ok(PortalFile {
+        leaves_num,
+        portals,
+    })
+}
+
+fn next_token<'a, T>(
+    tokens: &mut impl Iterator<Item = &'a [u8]>,
+    parse: fn(&[u8]) -> Option<T>,
+    label: &'static str,
+) -> ParsingResult<T> {
+    let token = tokens.next().ok_or(ParsingError::OutOfRange(label))?;
+    parse(token).ok_or(ParsingError::Invalid(label))
+}
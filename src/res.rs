@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bsp::{Level, entities::entities},
+    detail::DetailTexture,
+    error::ParsingResult,
+    skybox::{SKY_DIR, SKY_SUFFIXES, sky_name},
+};
+
+/// Resources a client must download to play a map.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceList {
+    /// Paths relative to the game directory, without duplicates.
+    pub resources: Vec<String>,
+}
+
+impl ResourceList {
+    /// Adds a path unless an equal one (ignoring case and slash style) is present.
+    pub fn add(&mut self, path: &str) {
+        let path = path.replace('\\', "/");
+        let path = path.trim_start_matches('/');
+        if path.is_empty()
+            || self
+                .resources
+                .iter()
+                .any(|resource| resource.eq_ignore_ascii_case(path))
+        {
+            return;
+        }
+        self.resources.push(path.to_owned());
+    }
+
+    /// Removes resources present in a base game directory (e.g. `valve`).
+    ///
+    /// Paths are compared case-insensitively, like the engine's file system
+    /// on Windows, so `models/Barney.mdl` matches `Models/barney.mdl`.
+    pub fn exclude_existing(&mut self, base_dir: &Path) {
+        let mut listings = HashMap::new();
+        self.resources
+            .retain(|resource| !exists_ignore_case(base_dir, resource, &mut listings));
+    }
+
+    /// Writes the list in `.res` format, one path per line.
+    pub fn write_res(&self, mut w: impl Write) -> io::Result<()> {
+        for resource in &self.resources {
+            writeln!(w, "{resource}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the resources referenced by a level.
+///
+/// Gathers models, sprites and sounds used by entities, WADs from the
+/// worldspawn `wad` key, the six `skyname` images and the detail textures
+/// (with `maps/<map_name>_detail.txt` itself when `details` isn't empty).
+pub fn map_resources(
+    level: &Level<'_>,
+    map_name: &str,
+    details: &[DetailTexture<'_>],
+) -> ParsingResult<ResourceList> {
+    let entities = entities(level.entities)?;
+    let mut list = ResourceList::default();
+
+    for entity in &entities {
+        for &(key, value) in &entity.pairs {
+            let Ok(value) = std::str::from_utf8(value) else {
+                continue;
+            };
+            let value = value.trim();
+            if key == b"wad" && entity.classname() == Some(b"worldspawn") {
+                for wad in value.split(';').filter(|wad| !wad.is_empty()) {
+                    let name = wad.rsplit(['/', '\\']).next().unwrap_or(wad);
+                    list.add(name);
+                }
+            } else if has_extension(value, "mdl") || has_extension(value, "spr") {
+                list.add(value);
+            } else if has_extension(value, "wav") {
+                // `*` marks streamed sounds.
+                let sound = value.trim_start_matches('*');
+                let sound = sound.strip_prefix("sound/").unwrap_or(sound);
+                list.add(&format!("sound/{sound}"));
+            }
+        }
+    }
+
+    if let Some(name) = sky_name(&entities) {
+        let name = String::from_utf8_lossy(name);
+        for suffix in SKY_SUFFIXES {
+            list.add(&format!("{SKY_DIR}/{name}{suffix}.tga"));
+        }
+    }
+
+    if !details.is_empty() {
+        list.add(&format!("maps/{map_name}_detail.txt"));
+        for detail in details {
+            list.add(&detail.path());
+        }
+    }
+
+    Ok(list)
+}
+
+/// Whether a `/` separated path exists under `base_dir`, ignoring ASCII case.
+///
+/// Directory listings are cached in `listings` between calls.
+fn exists_ignore_case(
+    base_dir: &Path,
+    path: &str,
+    listings: &mut HashMap<PathBuf, Vec<OsString>>,
+) -> bool {
+    if base_dir.join(path).exists() {
+        return true;
+    }
+
+    let mut dir = base_dir.to_path_buf();
+    for component in path.split('/') {
+        let names = listings.entry(dir.clone()).or_insert_with(|| {
+            fs::read_dir(&dir)
+                .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+                .unwrap_or_default()
+        });
+        let Some(name) = names.iter().find(|name| {
+            name.to_str()
+                .is_some_and(|name| name.eq_ignore_ascii_case(component))
+        }) else {
+            return false;
+        };
+        dir.push(name);
+    }
+
+    true
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case(extension))
+}
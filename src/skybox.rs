@@ -0,0 +1,365 @@
+use static_assertions::assert_eq_size;
+use zerocopy::{
+    FromBytes,
+    little_endian::{I32, U16, U32},
+};
+use zerocopy_derive::*;
+
+use crate::{
+    bsp::{
+        Level,
+        entities::{Entity, entities},
+    },
+    error::{ParsingError, ParsingResult},
+    texture::Rgba,
+    util::pixel_size,
+};
+
+/// Directory holding skybox images, relative to the game directory.
+pub const SKY_DIR: &str = "gfx/env";
+/// Face suffixes in the order the engine loads them.
+pub const SKY_SUFFIXES: [&str; 6] = ["rt", "bk", "lf", "ft", "up", "dn"];
+/// Image extensions tried for each face, in order.
+pub const SKY_EXTENSIONS: [&str; 2] = ["tga", "bmp"];
+
+/// TGA image type values.
+pub const TGA_TYPE_TRUECOLOR: u8 = 2;
+pub const TGA_TYPE_TRUECOLOR_RLE: u8 = 10;
+
+/// BMP magic.
+pub const BMP_MAGIC: [u8; 2] = *b"BM";
+
+/// Orientation of each face, indexed like `SKY_SUFFIXES`.
+///
+/// Each entry is the view direction towards the face center, then the world
+/// directions of the image's right and up (rows are stored top to bottom).
+pub const SKY_AXES: [[[f32; 3]; 3]; 6] = [
+    [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, -1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, 0.0, 1.0], [0.0, -1.0, 0.0], [-1.0, 0.0, 0.0]],
+    [[0.0, 0.0, -1.0], [0.0, -1.0, 0.0], [1.0, 0.0, 0.0]],
+];
+
+/// Decoded image.
+#[derive(Debug, Clone)]
+pub struct Image {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Row-major pixels, top row first.
+    pub pixels: Vec<Rgba>,
+}
+
+/// Skybox face.
+#[derive(Debug, Clone)]
+pub struct SkyFace {
+    /// Name suffix, e.g. `up`.
+    pub suffix: &'static str,
+    /// View direction towards the face center.
+    pub normal: [f32; 3],
+    /// World direction of the image's right.
+    pub right: [f32; 3],
+    /// World direction of the image's up.
+    pub up: [f32; 3],
+    /// Face image.
+    pub image: Image,
+}
+
+/// Skybox named by the worldspawn.
+#[derive(Debug, Clone)]
+pub struct Skybox<'a> {
+    /// Sky name (not guaranteed UTF-8).
+    pub name: &'a [u8],
+    /// Faces in `SKY_SUFFIXES` order.
+    pub faces: [SkyFace; 6],
+}
+
+/// TGA file header.
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct TgaHeader {
+    /// Length of the image id following the header.
+    pub id_length: u8,
+    /// Whether a color map is present.
+    pub color_map_type: u8,
+    /// Image type.
+    pub image_type: u8,
+    /// First color map entry.
+    pub color_map_first: U16,
+    /// Number of color map entries.
+    pub color_map_length: U16,
+    /// Bits per color map entry.
+    pub color_map_entry_size: u8,
+    /// Image origin.
+    pub origin: [U16; 2],
+    /// Width in pixels.
+    pub width: U16,
+    /// Height in pixels.
+    pub height: U16,
+    /// Bits per pixel.
+    pub pixel_depth: u8,
+    /// Alpha bits and origin corner.
+    pub descriptor: u8,
+}
+
+/// BMP file header followed by the info header.
+#[repr(C)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, KnownLayout, Immutable)]
+pub struct BmpHeader {
+    /// File magic ("BM").
+    pub magic: [u8; 2],
+    /// File size.
+    pub file_size: U32,
+    /// Reserved.
+    pub reserved: [U16; 2],
+    /// Offset of the pixel data.
+    pub data_offset: U32,
+    /// Info header size.
+    pub info_size: U32,
+    /// Width in pixels.
+    pub width: I32,
+    /// Height in pixels, negative for top-down images.
+    pub height: I32,
+    /// Number of planes.
+    pub planes: U16,
+    /// Bits per pixel.
+    pub bit_count: U16,
+    /// Compression method.
+    pub compression: U32,
+    /// Pixel data size.
+    pub image_size: U32,
+    /// Resolution.
+    pub pixels_per_meter: [I32; 2],
+    /// Number of palette entries, 0 for all.
+    pub colors_used: U32,
+    /// Number of important palette entries.
+    pub colors_important: U32,
+}
+
+/// Sky name set by the worldspawn `skyname` key.
+pub fn sky_name<'a>(entities: &[Entity<'a>]) -> Option<&'a [u8]> {
+    entities
+        .iter()
+        .find(|entity| entity.classname() == Some(b"worldspawn"))
+        .and_then(|worldspawn| worldspawn.get(b"skyname"))
+        .filter(|name| !name.is_empty())
+}
+
+/// Loads the skybox of a level.
+///
+/// `load` receives paths relative to the game directory (e.g.
+/// `gfx/env/desertup.tga`) and returns the file contents if it exists.
+/// Returns `None` when the worldspawn has no `skyname`.
+pub fn skybox<'a>(
+    level: &Level<'a>,
+    mut load: impl FnMut(&str) -> Option<Vec<u8>>,
+) -> ParsingResult<Option<Skybox<'a>>> {
+    let entities = entities(level.entities)?;
+    let Some(name) = sky_name(&entities) else {
+        return Ok(None);
+    };
+    let name_str = std::str::from_utf8(name).map_err(|_| ParsingError::Invalid("sky name"))?;
+
+    let mut faces = Vec::with_capacity(SKY_SUFFIXES.len());
+    for (suffix, [normal, right, up]) in SKY_SUFFIXES.into_iter().zip(SKY_AXES) {
+        let image = SKY_EXTENSIONS
+            .iter()
+            .find_map(|ext| {
+                let data = load(&format!("{SKY_DIR}/{name_str}{suffix}.{ext}"))?;
+                Some(if *ext == "tga" {
+                    tga_image(&data)
+                } else {
+                    bmp_image(&data)
+                })
+            })
+            .ok_or(ParsingError::OutOfRange("sky face image"))??;
+
+        faces.push(SkyFace {
+            suffix,
+            normal,
+            right,
+            up,
+            image,
+        });
+    }
+
+    Ok(Some(Skybox {
+        name,
+        faces: faces
+            .try_into()
+            .map_err(|_| ParsingError::Invalid("sky faces"))?,
+    }))
+}
+
+/// Decodes a 24/32-bit TGA image, uncompressed or RLE.
+pub fn tga_image(bytes: &[u8]) -> ParsingResult<Image> {
+    let (header, rest) =
+        TgaHeader::ref_from_prefix(bytes).map_err(|_| ParsingError::OutOfRange("tga header"))?;
+    if header.image_type != TGA_TYPE_TRUECOLOR && header.image_type != TGA_TYPE_TRUECOLOR_RLE {
+        return Err(ParsingError::Invalid("tga image type"));
+    }
+    let bytes_per_pixel = match header.pixel_depth {
+        24 => 3,
+        32 => 4,
+        _ => return Err(ParsingError::Invalid("tga pixel depth")),
+    };
+
+    let color_map_size = if header.color_map_type != 0 {
+        usize::from(header.color_map_length.get())
+            * usize::from(header.color_map_entry_size).div_ceil(8)
+    } else {
+        0
+    };
+    let data = rest
+        .get(usize::from(header.id_length) + color_map_size..)
+        .ok_or(ParsingError::OutOfRange("tga data"))?;
+
+    let width = u32::from(header.width.get());
+    let height = u32::from(header.height.get());
+    let count = pixel_size(width, height, "tga pixels")?;
+
+    let to_rgba = |pixel: &[u8]| -> Rgba {
+        let alpha = if bytes_per_pixel == 4 { pixel[3] } else { 255 };
+        [pixel[2], pixel[1], pixel[0], alpha]
+    };
+
+    let mut pixels: Vec<Rgba> = if header.image_type == TGA_TYPE_TRUECOLOR {
+        // Check the data holds every pixel before allocating them.
+        let data = data
+            .get(..count * bytes_per_pixel)
+            .ok_or(ParsingError::OutOfRange("tga pixels"))?;
+        data.chunks_exact(bytes_per_pixel).map(to_rgba).collect()
+    } else {
+        // The pixel count of RLE data is only known after decoding it, so
+        // pixels are not reserved upfront.
+        let mut pixels = Vec::new();
+        let mut pos = 0;
+        while pixels.len() < count {
+            let packet = *data
+                .get(pos)
+                .ok_or(ParsingError::OutOfRange("tga packet"))?;
+            let len = usize::from(packet & 0x7F) + 1;
+            if len > count - pixels.len() {
+                return Err(ParsingError::Invalid("tga packet length"));
+            }
+            let is_run = packet & 0x80 != 0;
+            let size = if is_run {
+                bytes_per_pixel
+            } else {
+                len * bytes_per_pixel
+            };
+            let packet_data = data
+                .get(pos + 1..pos + 1 + size)
+                .ok_or(ParsingError::OutOfRange("tga packet"))?;
+            if is_run {
+                pixels.extend(std::iter::repeat_n(to_rgba(packet_data), len));
+            } else {
+                pixels.extend(packet_data.chunks_exact(bytes_per_pixel).map(to_rgba));
+            }
+            pos += 1 + size;
+        }
+        pixels
+    };
+
+    if header.descriptor & 0x10 != 0 && width != 0 {
+        // Right-to-left rows.
+        pixels
+            .chunks_exact_mut(width as usize)
+            .for_each(<[Rgba]>::reverse);
+    }
+    if header.descriptor & 0x20 == 0 {
+        // Bottom-up rows.
+        flip_rows(&mut pixels, width as usize);
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Decodes an uncompressed 8-bit BMP image.
+pub fn bmp_image(bytes: &[u8]) -> ParsingResult<Image> {
+    let (header, _) =
+        BmpHeader::ref_from_prefix(bytes).map_err(|_| ParsingError::OutOfRange("bmp header"))?;
+    if header.magic != BMP_MAGIC {
+        return Err(ParsingError::Invalid("bmp magic"));
+    }
+    if header.bit_count.get() != 8 {
+        return Err(ParsingError::Invalid("bmp bit count"));
+    }
+    if header.compression.get() != 0 {
+        return Err(ParsingError::Invalid("bmp compression"));
+    }
+
+    let width = header.width.get();
+    let height = header.height.get();
+    if width < 0 {
+        return Err(ParsingError::Invalid("bmp width"));
+    }
+    let width = width.unsigned_abs();
+    let top_down = height < 0;
+    let height = height.unsigned_abs();
+    let count = pixel_size(width, height, "bmp pixels")?;
+
+    let colors_num = match header.colors_used.get() {
+        0 => 256,
+        n => usize::try_from(n).map_err(|_| ParsingError::NumberOverflow("bmp palette"))?,
+    };
+    let palette_offset = 14
+        + usize::try_from(header.info_size.get())
+            .map_err(|_| ParsingError::NumberOverflow("bmp palette"))?;
+    let palette = bytes
+        .get(palette_offset..)
+        .and_then(|bytes| bytes.get(..colors_num.checked_mul(4)?))
+        .ok_or(ParsingError::OutOfRange("bmp palette"))?;
+
+    let row_size = (width as usize).div_ceil(4) * 4;
+    let data_offset = usize::try_from(header.data_offset.get())
+        .map_err(|_| ParsingError::NumberOverflow("bmp data"))?;
+    let data = bytes
+        .get(data_offset..)
+        .and_then(|bytes| bytes.get(..row_size.checked_mul(height as usize)?))
+        .ok_or(ParsingError::OutOfRange("bmp data"))?;
+
+    // `data` was checked to hold every row, so pixels are only allocated
+    // for data that exists.
+    let mut pixels = data
+        .chunks_exact(row_size.max(1))
+        .flat_map(|row| &row[..width as usize])
+        .map(|&index| {
+            palette
+                .get(usize::from(index) * 4..usize::from(index) * 4 + 3)
+                .map(|color| [color[2], color[1], color[0], 255])
+                .ok_or(ParsingError::OutOfRange("bmp palette index"))
+        })
+        .collect::<ParsingResult<Vec<_>>>()?;
+    pixels.truncate(count);
+    if !top_down {
+        flip_rows(&mut pixels, width as usize);
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn flip_rows(pixels: &mut [Rgba], width: usize) {
+    if width == 0 {
+        return;
+    }
+    let height = pixels.len() / width;
+    for row in 0..height / 2 {
+        let (top, bottom) = pixels.split_at_mut((height - row - 1) * width);
+        top[row * width..(row + 1) * width].swap_with_slice(&mut bottom[..width]);
+    }
+}
+
+assert_eq_size!(TgaHeader, [u8; 18]);
+assert_eq_size!(BmpHeader, [u8; 54]);
@@ -43,6 +43,8 @@ pub const SPRITE_FRAME_TYPE_GROUP: u32 = 1;
 
 /// RGB color as three u8 values (red, green, blue).
 pub type Rgb = [u8; 3];
+/// RGBA color as four u8 values (red, green, blue, alpha).
+pub type Rgba = [u8; 4];
 /// Index into a palette.
 pub type PaletteIndex = u8;
 
@@ -371,3 +373,4 @@ assert_eq_size!(CharInfo, [u8; 4]);
 assert_eq_size!(SpriteHeader, [u8; 40]);
 assert_eq_size!(SpriteFrameHeader, [u8; 16]);
 assert_eq_size!(Rgb, [u8; 3]);
+assert_eq_size!(Rgba, [u8; 4]);
@@ -79,3 +79,11 @@ pub fn pixel_size(width: u32, height: u32, kind: &'static str) -> ParsingResult<
         .ok_or(ParsingError::NumberOverflow(kind))?;
     usize::try_from(size).map_err(|_| ParsingError::NumberOverflow(kind))
 }
+
+pub fn parse_f32(bytes: &[u8]) -> Option<f32> {
+    std::str::from_utf8(bytes).ok()?.trim().parse().ok()
+}
+
+pub fn parse_usize(bytes: &[u8]) -> Option<usize> {
+    std::str::from_utf8(bytes).ok()?.trim().parse().ok()
+}
@@ -1,4 +1,30 @@
-use goldsrc_rs::bsp::level;
+use goldsrc_rs::{
+    bsp::{
+        BSP_VERSION, CONTENTS_EMPTY, CONTENTS_SOLID, ClipNode, Edge, Face, LUMP_EDGES,
+        LUMP_ENTITIES, LUMP_PLANES, Leaf, LevelHeader, Model, Node, Plane, TEXTURE_SPECIAL,
+        TextureInfo,
+        adjacency::{FaceGraph, TJunction},
+        bake::{BakeOptions, bake_level},
+        entities::entities,
+        hull::{HULL_HUMAN, HULL_POINT, hull_contents, point_contents, trace},
+        level,
+        lightstyle::{LightStyles, composite_lightmap},
+        nav::{NavLinkKind, NavOptions, build_nav_graph},
+        query,
+        raycast::raycast,
+        render::SurfaceWalker,
+        stats::stats,
+        texture_name::{TextureKind, classify, texture_animations},
+        vis::{leaf_pvs, point_leaf},
+        warp::{WarpKind, face_warp_kind, subdivide_face, turbulent_coords, warp_kind},
+    },
+    common::{BBox, Lump, Vec3f},
+    texture::MipTextureHeader,
+};
+use zerocopy::{
+    IntoBytes,
+    little_endian::{F32, I16, I32, U16, U32},
+};
 
 #[test]
 fn parse_bsp() {
@@ -47,3 +73,1086 @@ fn parse_bsp() {
         }
     }
 }
+
+#[test]
+fn query_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+        let Some(world) = level.models.first() else {
+            continue;
+        };
+
+        let bounds = BBox {
+            min: world.bounds.min.map(|v| v.get()),
+            max: world.bounds.max.map(|v| v.get()),
+        };
+
+        let mut leaves = 0;
+        query::leaves_in_box(&level, &bounds, |leaf_id| {
+            assert!(leaf_id < level.leaves.len());
+            leaves += 1;
+        })
+        .unwrap();
+
+        let mut faces = 0;
+        query::faces_in_sphere(&level, [0.0; 3], 256.0, |face_id| {
+            assert!(face_id < level.faces.len());
+            faces += 1;
+        })
+        .unwrap();
+
+        println!("Leaves in world bounds: {leaves}");
+        println!("Faces near origin: {faces}");
+    }
+}
+
+#[test]
+fn query_room() {
+    let cyclic_data = Room::cyclic().bytes();
+    let cyclic = level(&cyclic_data).unwrap();
+    // Both children of each node lead to the next one, so a walk through
+    // both sides would take 2^n steps.
+    let mut shared = Room::new();
+    for (node_id, node) in shared.nodes[..5].iter_mut().enumerate() {
+        node.children = [I16::new(node_id as i16 + 1); 2];
+    }
+    let shared_data = shared.bytes();
+    let shared = level(&shared_data).unwrap();
+    let data = Room::new().bytes();
+    let level = level(&data).unwrap();
+
+    let inside = BBox {
+        min: [-8.0, -8.0, 8.0],
+        max: [8.0, 8.0, 24.0],
+    };
+    let mut leaves = Vec::new();
+    query::leaves_in_box(&level, &inside, |leaf_id| leaves.push(leaf_id)).unwrap();
+    assert_eq!(leaves, [1]);
+
+    let mut faces = Vec::new();
+    query::faces_in_sphere(&level, [0.0, 0.0, 64.0], 16.0, |face_id| {
+        faces.push(face_id)
+    })
+    .unwrap();
+    assert_eq!(faces, [0, 1, 2, 3, 4, 5]);
+
+    let below = BBox {
+        min: [-8.0, -8.0, -32.0],
+        max: [8.0, 8.0, -16.0],
+    };
+    let mut faces = Vec::new();
+    query::faces_in_box(&level, &below, |face_id| faces.push(face_id)).unwrap();
+    assert!(faces.is_empty());
+
+    let everything = BBox {
+        min: [-128.0, -128.0, -64.0],
+        max: [128.0, 128.0, 192.0],
+    };
+    let mut leaves = Vec::new();
+    query::leaves_in_box(&level, &everything, |leaf_id| leaves.push(leaf_id)).unwrap();
+    assert_eq!(leaves, [1]);
+
+    assert!(query::leaves_in_box(&cyclic, &inside, |_| {}).is_err());
+    assert!(query::leaves_in_box(&shared, &everything, |_| {}).is_err());
+    assert!(query::faces_in_box(&shared, &everything, |_| {}).is_err());
+}
+
+#[test]
+fn raycast_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+        let Some(world) = level.models.first() else {
+            continue;
+        };
+
+        let min = world.bounds.min.map(|v| v.get());
+        let max = world.bounds.max.map(|v| v.get());
+        let center = [0, 1, 2].map(|i| (min[i] + max[i]) * 0.5);
+        let start = [center[0], center[1], max[2]];
+        let end = [center[0], center[1], min[2]];
+
+        if let Some(hit) = raycast(&level, start, end).unwrap() {
+            assert!(hit.face_id < level.faces.len());
+            assert!((0.0..=1.0).contains(&hit.fraction));
+            println!(
+                "Hit: face={} texture={} position={:?}",
+                hit.face_id,
+                String::from_utf8_lossy(hit.texture_name),
+                hit.position
+            );
+        }
+    }
+}
+
+#[test]
+fn raycast_room() {
+    let cyclic_data = Room::cyclic().bytes();
+    let cyclic = level(&cyclic_data).unwrap();
+    let data = Room::new().bytes();
+    let level = level(&data).unwrap();
+
+    let hit = raycast(&level, [8.0, 4.0, 64.0], [8.0, 4.0, -64.0])
+        .unwrap()
+        .unwrap();
+    assert_eq!(hit.face_id, 0);
+    assert_eq!(hit.texture_name, b"floor");
+    assert_eq!(hit.position, [8.0, 4.0, 0.0]);
+    assert_eq!(hit.normal, [0.0, 0.0, 1.0]);
+    assert_eq!(hit.fraction, 0.5);
+    assert_eq!(hit.tex_coords, [8.0, 4.0]);
+    assert!(hit.lightmap_coords.is_some());
+
+    let hit = raycast(&level, [0.0, 0.0, 64.0], [100.0, 0.0, 64.0])
+        .unwrap()
+        .unwrap();
+    assert_eq!(hit.face_id, 3);
+    assert_eq!(hit.position, [64.0, 0.0, 64.0]);
+    assert_eq!(hit.normal, [-1.0, 0.0, 0.0]);
+
+    let miss = raycast(&level, [0.0, 0.0, 32.0], [0.0, 0.0, 96.0]).unwrap();
+    assert!(miss.is_none());
+
+    assert!(raycast(&cyclic, [0.0, 0.0, 64.0], [0.0, 0.0, -64.0]).is_err());
+}
+
+#[test]
+fn walk_visible_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+        let Some(start) = level
+            .leaves
+            .iter()
+            .skip(1)
+            .find(|leaf| leaf.contents.get() == CONTENTS_EMPTY)
+        else {
+            continue;
+        };
+        let origin = [0, 1, 2].map(|i| {
+            (f32::from(start.bounds.min[i].get()) + f32::from(start.bounds.max[i].get())) * 0.5
+        });
+
+        let mut walker = SurfaceWalker::new(&level).unwrap();
+        let mut visible = 0;
+        walker
+            .walk(&level, origin, &[], |face_id| {
+                assert!(face_id < level.faces.len());
+                visible += 1;
+            })
+            .unwrap();
+
+        println!("Visible faces from {origin:?}: {visible}");
+    }
+}
+
+#[test]
+fn walk_visible_room() {
+    let cyclic_data = Room::cyclic().bytes();
+    let cyclic = level(&cyclic_data).unwrap();
+    // A walker created for a level with fewer faces than the one it walks.
+    let mut fewer_faces = Room::new();
+    fewer_faces.faces.pop();
+    fewer_faces.nodes[5].faces_num = U16::new(0);
+    let fewer_faces_data = fewer_faces.bytes();
+    let fewer_faces = level(&fewer_faces_data).unwrap();
+    let mut hidden_face = Room::new();
+    hidden_face.leaves[1].mark_surfaces_num = U16::new(5);
+    let hidden_face_data = hidden_face.bytes();
+    let hidden_face = level(&hidden_face_data).unwrap();
+    let data = Room::new().bytes();
+    let level = level(&data).unwrap();
+
+    assert_eq!(point_leaf(&level, [0.0, 0.0, 64.0]).unwrap(), 1);
+    assert_eq!(point_leaf(&level, [0.0, 0.0, -64.0]).unwrap(), 0);
+    let pvs = leaf_pvs(&level, 1).unwrap();
+    assert_eq!(pvs.bits, [0x01]);
+    assert!(pvs.is_visible(1));
+    assert!(!pvs.is_visible(0));
+
+    let mut walker = SurfaceWalker::new(&level).unwrap();
+    let mut faces = Vec::new();
+    walker
+        .walk(&level, [0.0, 0.0, 64.0], &[], |face_id| faces.push(face_id))
+        .unwrap();
+    faces.sort();
+    assert_eq!(faces, [0, 1, 2, 3, 4, 5]);
+
+    assert!(point_leaf(&cyclic, [0.0, 0.0, 64.0]).is_err());
+    assert!(SurfaceWalker::new(&cyclic).is_err());
+
+    let mut walker = SurfaceWalker::new(&fewer_faces).unwrap();
+    assert!(
+        walker
+            .walk(&hidden_face, [0.0, 0.0, 64.0], &[], |_| {})
+            .is_err()
+    );
+}
+
+#[test]
+fn lightstyles_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+        let entities = entities(level.entities).unwrap();
+        let styles = LightStyles::from_entities(&entities);
+        println!("Entities: {}", entities.len());
+
+        let mut lightmaps = 0;
+        for face in level.faces {
+            if let Some(lightmap) = composite_lightmap(&level, face, &styles, 1.5).unwrap() {
+                assert_eq!(
+                    lightmap.samples.len(),
+                    (lightmap.width * lightmap.height) as usize
+                );
+                lightmaps += 1;
+            }
+        }
+        println!("Lightmaps: {lightmaps}");
+    }
+}
+
+#[test]
+fn lightstyles_room() {
+    let mut room = Room::new();
+    room.entities.push_str(concat!(
+        "{\n\"classname\" \"light\"\n\"origin\" \"0 0 64\"\n",
+        "\"style\" \"32\"\n\"pattern\" \"az\"\n}\n",
+        "{\n\"classname\" \"light_spot\"\n\"style\" \"33\"\n\"spawnflags\" \"1\"\n}\n",
+    ));
+    room.faces[0].lighting_styles = [0, 32, 255, 255];
+    room.faces[0].lightmap_offset = U32::new(room.lighting.len() as u32);
+    room.lighting.extend([100; ROOM_LIGHTMAP_SIZE * 3]);
+    room.lighting.extend([50; ROOM_LIGHTMAP_SIZE * 3]);
+    let data = room.bytes();
+    let level = level(&data).unwrap();
+
+    let entities = entities(level.entities).unwrap();
+    assert_eq!(entities.len(), 3);
+    assert_eq!(entities[1].classname(), Some(&b"light"[..]));
+    assert_eq!(entities[1].get_vec3(b"origin"), Some([0.0, 0.0, 64.0]));
+
+    let styles = LightStyles::from_entities(&entities);
+    assert_eq!(styles.patterns[32], b"az");
+    assert_eq!(styles.patterns[33], b"a");
+    assert_eq!(styles.value(0, 0.0), 264);
+    assert_eq!(styles.value(32, 0.0), 0);
+    assert_eq!(styles.value(32, 0.1), 550);
+
+    let lightmap = composite_lightmap(&level, &level.faces[0], &styles, 0.0)
+        .unwrap()
+        .unwrap();
+    assert_eq!((lightmap.width, lightmap.height), (9, 9));
+    assert_eq!(lightmap.samples.len(), ROOM_LIGHTMAP_SIZE);
+    assert!(lightmap.samples.iter().all(|&sample| sample == [103; 3]));
+
+    let lightmap = composite_lightmap(&level, &level.faces[0], &styles, 0.1)
+        .unwrap()
+        .unwrap();
+    assert!(lightmap.samples.iter().all(|&sample| sample == [210; 3]));
+}
+
+#[test]
+fn texture_names_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+
+        for texture in &level.textures {
+            let class = classify(&texture.header.name);
+            if class.kind != TextureKind::Normal {
+                println!(
+                    "  {} {:?}",
+                    String::from_utf8_lossy(class.base_name),
+                    class.kind
+                );
+            }
+        }
+
+        for animation in texture_animations(&level).unwrap() {
+            assert!(animation.frame(0.0, false).is_some());
+            println!(
+                "Animation {}: frames={} alternate={}",
+                String::from_utf8_lossy(animation.base_name),
+                animation.frames.len(),
+                animation.alternate_frames.len()
+            );
+        }
+    }
+}
+
+#[test]
+fn texture_names_room() {
+    let mut room = Room::new();
+    let names: [&[u8]; 6] = [
+        b"+0fan",
+        b"{grate",
+        b"+1FAN",
+        b"-3rock",
+        b"+afan",
+        b"scrollbelt",
+    ];
+    for (texture_id, name) in names.iter().enumerate() {
+        room.rename_texture(texture_id, name);
+    }
+    let data = room.bytes();
+    room.rename_texture(2, b"+2fan");
+    let gap_data = room.bytes();
+    let gap = level(&gap_data).unwrap();
+    let level = level(&data).unwrap();
+
+    let kinds: Vec<_> = level
+        .textures
+        .iter()
+        .map(|texture| classify(&texture.header.name))
+        .map(|class| (class.kind, class.base_name))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (
+                TextureKind::Animated {
+                    frame: 0,
+                    alternate: false
+                },
+                &b"fan"[..]
+            ),
+            (TextureKind::Masked, b"grate"),
+            (
+                TextureKind::Animated {
+                    frame: 1,
+                    alternate: false
+                },
+                b"FAN"
+            ),
+            (TextureKind::RandomTiled { variant: 3 }, b"rock"),
+            (
+                TextureKind::Animated {
+                    frame: 0,
+                    alternate: true
+                },
+                b"fan"
+            ),
+            (TextureKind::Scroll, b"scrollbelt"),
+        ]
+    );
+    for (name, base_name) in [(&b"!toxic"[..], &b"toxic"[..]), (b"*lava1", b"lava1")] {
+        let class = classify(name);
+        assert_eq!(class.kind, TextureKind::Water);
+        assert_eq!(class.base_name, base_name);
+    }
+
+    let animations = texture_animations(&level).unwrap();
+    assert_eq!(animations.len(), 1);
+    let animation = &animations[0];
+    assert_eq!(animation.frames, [0, 2]);
+    assert_eq!(animation.alternate_frames, [4]);
+    assert_eq!(animation.frame(0.0, false), Some(0));
+    assert_eq!(animation.frame(0.1, false), Some(2));
+    assert_eq!(animation.frame(0.2, false), Some(0));
+    assert_eq!(animation.frame(0.1, true), Some(4));
+
+    assert!(texture_animations(&gap).is_err());
+}
+
+#[test]
+fn adjacency_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+        let graph = FaceGraph::new(&level).unwrap();
+
+        for (face_id, neighbors) in graph.face_neighbors.iter().enumerate() {
+            for &neighbor in neighbors {
+                assert!(graph.face_neighbors[neighbor].contains(&face_id));
+            }
+        }
+
+        println!("Boundary edges: {}", graph.boundary_edges.len());
+        println!("T-junctions: {}", graph.t_junctions.len());
+    }
+}
+
+#[test]
+fn adjacency_room() {
+    let graph = FaceGraph::new(&level(&Room::new().bytes()).unwrap()).unwrap();
+
+    assert_eq!(graph.edge_faces.len(), 13);
+    assert!(graph.edge_faces[0].is_empty());
+    assert!(graph.edge_faces[1..].iter().all(|uses| uses.len() == 2));
+    assert_eq!(graph.face_neighbors[0], [2, 3, 4, 5]);
+    assert_eq!(graph.face_neighbors[3], [0, 1, 4, 5]);
+    assert!(graph.boundary_edges.is_empty());
+    assert!(graph.t_junctions.is_empty());
+
+    // Split the ceiling edge shared with the south wall at its middle.
+    let mut room = Room::new();
+    assert_eq!(room.edges[5].map(|id| id.get()), [4, 5]);
+    room.vertices.push([0.0, -64.0, 128.0].map(F32::new));
+    room.edges.push([U16::new(4), U16::new(8)]);
+    room.edges.push([U16::new(8), U16::new(5)]);
+    room.faces[1].first_surfedge_id = U32::new(room.surfedges.len() as u32);
+    room.faces[1].surfedges_num = U16::new(5);
+    room.surfedges.extend([13, 14, 6, 7, 8].map(I32::new));
+    let graph = FaceGraph::new(&level(&room.bytes()).unwrap()).unwrap();
+
+    assert_eq!(graph.boundary_edges, [5, 13, 14]);
+    assert_eq!(graph.edge_pair(5), (None, Some(4)));
+    assert_eq!(graph.face_neighbors[1], [2, 3, 5]);
+    assert_eq!(graph.face_neighbors[4], [0, 2, 3]);
+    assert_eq!(
+        graph.t_junctions,
+        [TJunction {
+            edge_id: 5,
+            vertex_id: 8
+        }]
+    );
+}
+
+#[test]
+fn hull_room() {
+    let cyclic_data = Room::cyclic().bytes();
+    let cyclic = level(&cyclic_data).unwrap();
+    let data = Room::new().bytes();
+    let level = level(&data).unwrap();
+
+    assert_eq!(
+        point_contents(&level, 0, HULL_HUMAN, [0.0, 0.0, 64.0]).unwrap(),
+        CONTENTS_EMPTY
+    );
+    assert_eq!(
+        point_contents(&level, 0, HULL_HUMAN, [0.0, 0.0, 20.0]).unwrap(),
+        CONTENTS_SOLID
+    );
+    assert_eq!(
+        point_contents(&level, 0, HULL_POINT, [0.0, 0.0, 20.0]).unwrap(),
+        CONTENTS_EMPTY
+    );
+
+    let fall = trace(&level, 0, HULL_HUMAN, [0.0, 0.0, 64.0], [0.0, 0.0, -100.0]).unwrap();
+    assert!(!fall.start_solid && !fall.all_solid && fall.in_open);
+    assert_eq!(fall.plane_normal, [0.0, 0.0, 1.0]);
+    assert_eq!(fall.plane_distance, 36.0);
+    assert!((fall.end_pos[2] - 36.03125).abs() < 1e-3);
+    assert!((fall.fraction - (64.0 - 36.03125) / 164.0).abs() < 1e-4);
+
+    let walk = trace(&level, 0, HULL_HUMAN, [0.0, 0.0, 64.0], [100.0, 0.0, 64.0]).unwrap();
+    assert_eq!(walk.plane_normal, [-1.0, 0.0, 0.0]);
+    assert!((walk.end_pos[0] - 47.96875).abs() < 1e-3);
+
+    let free = trace(&level, 0, HULL_POINT, [0.0, 0.0, 64.0], [0.0, 0.0, 8.0]).unwrap();
+    assert_eq!(free.fraction, 1.0);
+    assert_eq!(free.end_pos, [0.0, 0.0, 8.0]);
+
+    let mut contents = Vec::new();
+    hull_contents(&level, 0, HULL_HUMAN, |c| contents.push(c)).unwrap();
+    assert_eq!(contents.len(), 7);
+    assert_eq!(contents.iter().filter(|&&c| c == CONTENTS_EMPTY).count(), 1);
+
+    for hull in [HULL_POINT, HULL_HUMAN] {
+        assert!(trace(&cyclic, 0, hull, [0.0, 0.0, 64.0], [0.0, 0.0, -100.0]).is_err());
+        assert!(point_contents(&cyclic, 0, hull, [0.0, 0.0, 64.0]).is_err());
+    }
+}
+
+#[test]
+fn nav_room() {
+    let data = Room::new().bytes();
+    let level = level(&data).unwrap();
+    let graph = build_nav_graph(&level, &NavOptions::default()).unwrap();
+
+    // A 3 x 3 grid of floor nodes, each linked to its eight neighbours.
+    assert_eq!(graph.nodes.len(), 9);
+    for node in &graph.nodes {
+        assert_eq!(node.origin[2], 36.03125);
+        assert_eq!(node.normal, [0.0, 0.0, 1.0]);
+        assert_eq!(node.flags, 0);
+    }
+    assert_eq!(graph.nodes[4].origin, [-16.0, -16.0, 36.03125]);
+    assert_eq!(graph.links.len(), 40);
+    for link in &graph.links {
+        assert_eq!(link.kind, NavLinkKind::Walk);
+        assert!(
+            graph
+                .links
+                .iter()
+                .any(|back| back.from == link.to && back.to == link.from)
+        );
+    }
+    assert_eq!(graph.links.iter().filter(|link| link.from == 4).count(), 8);
+
+    let mut text = Vec::new();
+    graph.write_text(&mut text).unwrap();
+    assert!(text.starts_with(b"NAV 1\nnodes 9\n-48 -48 36.03125 0 0 1 0\n"));
+
+    let options = NavOptions {
+        cell_size: 0.0,
+        ..Default::default()
+    };
+    assert!(build_nav_graph(&level, &options).is_err());
+}
+
+#[test]
+fn nav_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+        let options = NavOptions {
+            cell_size: 128.0,
+            ..Default::default()
+        };
+        let graph = build_nav_graph(&level, &options).unwrap();
+
+        for link in &graph.links {
+            assert!(link.from < graph.nodes.len() && link.to < graph.nodes.len());
+        }
+
+        let mut text = Vec::new();
+        graph.write_text(&mut text).unwrap();
+        println!("Nav nodes: {}", graph.nodes.len());
+        println!("Nav links: {}", graph.links.len());
+    }
+}
+
+#[test]
+fn stats_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+        let stats = stats(&level).unwrap();
+
+        assert!(stats.file_size <= data.len());
+        for lump in &stats.lumps {
+            println!(
+                "{:<12} {:>6} {:>8} {:>5.1}%",
+                lump.name, lump.count, lump.size, lump.percentage
+            );
+        }
+        println!("Lightmap bytes: {}", stats.lightmap_bytes);
+        println!("Vis compression: {:.1}", stats.vis_compression_ratio);
+    }
+}
+
+#[test]
+fn stats_room() {
+    let data = Room::new().bytes();
+    let level = level(&data).unwrap();
+    let stats = stats(&level).unwrap();
+
+    assert_eq!(stats.file_size, data.len());
+    let planes = &stats.lumps[LUMP_PLANES];
+    assert_eq!(
+        (planes.name, planes.count, planes.size),
+        ("planes", 12, 240)
+    );
+    assert_eq!(planes.percentage, 240.0 * 100.0 / data.len() as f32);
+    assert_eq!(stats.lumps[LUMP_ENTITIES].count, 1);
+    assert_eq!(stats.lumps[LUMP_EDGES].count, 13);
+    let total: f32 = stats.lumps.iter().map(|lump| lump.percentage).sum();
+    assert!(total < 100.0);
+
+    assert_eq!(stats.embedded_textures, 0);
+    assert_eq!(stats.external_textures, 6);
+    // Palette plus four mip levels of a 64 x 64 texture.
+    assert_eq!(stats.external_texture_bytes, 6 * (2 + 768 + 5440));
+    assert_eq!(stats.lit_faces, 6);
+    assert_eq!(stats.lightmap_bytes, 6 * ROOM_LIGHTMAP_SIZE * 3);
+    assert_eq!(stats.average_face_vertices, 4.0);
+    assert_eq!(stats.vis_clusters, 1);
+    assert_eq!(stats.visdata_uncompressed, 1);
+    assert_eq!(stats.vis_compression_ratio, 1.0);
+}
+
+#[test]
+fn bake_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        let data = std::fs::read(&path).expect("error reading file");
+        // Baking is slow, only small test maps are relit.
+        if level(&data).unwrap().faces.len() > 1000 {
+            continue;
+        }
+        println!("File: {:?}", path);
+
+        let baked = bake_level(&data, &BakeOptions::default()).unwrap();
+        let level = level(&baked).unwrap();
+        let stats = stats(&level).unwrap();
+        assert_eq!(level.lighting.len(), stats.lightmap_bytes);
+    }
+}
+
+#[test]
+fn bake_unlit_room() {
+    let mut room = Room::unlit();
+    room.entities.push_str(
+        "{\n\"classname\" \"light\"\n\"origin\" \"0 0 64\"\n\"_light\" \"255 128 64 32\"\n}\n",
+    );
+    room.texture_infos[1].flags = U32::new(TEXTURE_SPECIAL);
+    let data = room.bytes();
+    assert!(level(&data).unwrap().lighting.is_empty());
+
+    let baked = bake_level(&data, &BakeOptions::default()).unwrap();
+    let level = level(&baked).unwrap();
+    assert_eq!(level.lighting.len(), 5 * ROOM_LIGHTMAP_SIZE * 3);
+    for (face_id, face) in level.faces.iter().enumerate() {
+        if face_id == 1 {
+            assert_eq!(face.lightmap_offset.get(), u32::MAX);
+            assert_eq!(face.lighting_styles, [255; 4]);
+        } else {
+            assert_ne!(face.lightmap_offset.get(), u32::MAX);
+            assert_eq!(face.lighting_styles, [0, 255, 255, 255]);
+        }
+    }
+
+    // The floor below the light is lit with the light color.
+    let lightmap = composite_lightmap(&level, &level.faces[0], &LightStyles::default(), 0.0)
+        .unwrap()
+        .unwrap();
+    let [r, g, b] = lightmap.samples[ROOM_LIGHTMAP_SIZE / 2];
+    assert!(r > g && g > b && b > 0);
+    assert!(lightmap.samples[0][0] < r);
+}
+
+#[test]
+fn warp_bsp() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+
+        let mut polygons_num = 0;
+        for face in level.faces {
+            if face_warp_kind(&level, face).unwrap().is_none() {
+                continue;
+            }
+            for polygon in subdivide_face(&level, face).unwrap() {
+                assert!(polygon.len() >= 3);
+                for vertex in &polygon {
+                    let coords = turbulent_coords(vertex.tex_coords, 1.0);
+                    assert!(coords.iter().all(|v| v.is_finite()));
+                }
+                polygons_num += 1;
+            }
+        }
+        println!("Warped polygons: {polygons_num}");
+    }
+}
+
+#[test]
+fn warp_kinds_match_texture_classes() {
+    let names: [(&[u8], Option<WarpKind>); 12] = [
+        (b"sky", Some(WarpKind::Sky)),
+        (b"SKY_CLOUDS", Some(WarpKind::Sky)),
+        (b"!toxic", Some(WarpKind::Turbulent)),
+        (b"water1", Some(WarpKind::Turbulent)),
+        (b"Laser_beam", Some(WarpKind::Turbulent)),
+        (b"*lava1", Some(WarpKind::Turbulent)),
+        (b"+0water", None),
+        (b"{water", None),
+        (b"-1sky", None),
+        (b"aaatrigger", None),
+        (b"floor", None),
+        (b"", None),
+    ];
+
+    for (name, kind) in names {
+        assert_eq!(warp_kind(name), kind);
+        let class = classify(name).kind;
+        assert_eq!(class == TextureKind::Sky, kind == Some(WarpKind::Sky));
+        assert_eq!(
+            class == TextureKind::Water,
+            kind == Some(WarpKind::Turbulent)
+        );
+    }
+}
+
+#[test]
+fn warp_room() {
+    let mut room = Room::new();
+    room.rename_texture(0, b"!water");
+    room.rename_texture(1, b"sky");
+    room.texture_infos[0].flags = U32::new(TEXTURE_SPECIAL);
+    room.texture_infos[1].flags = U32::new(TEXTURE_SPECIAL);
+    let data = room.bytes();
+    let level = level(&data).unwrap();
+
+    let kinds: Vec<_> = level
+        .faces
+        .iter()
+        .map(|face| face_warp_kind(&level, face).unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            Some(WarpKind::Turbulent),
+            Some(WarpKind::Sky),
+            None,
+            None,
+            None,
+            None
+        ]
+    );
+
+    // The 128 x 128 floor is cut into four 64 x 64 quads.
+    let polygons = subdivide_face(&level, &level.faces[0]).unwrap();
+    assert_eq!(polygons.len(), 4);
+    for polygon in &polygons {
+        assert_eq!(polygon.len(), 4);
+        for vertex in polygon {
+            assert_eq!(vertex.position[2], 0.0);
+            assert_eq!(vertex.tex_coords, [vertex.position[0], vertex.position[1]]);
+        }
+        let xs = polygon.iter().map(|vertex| vertex.position[0]);
+        let width = xs.clone().fold(f32::MIN, f32::max) - xs.fold(f32::MAX, f32::min);
+        assert_eq!(width, 64.0);
+    }
+
+    assert_eq!(turbulent_coords([0.0, 0.0], 0.0), [0.0, 0.0]);
+    let [s, t] = turbulent_coords([64.0, 32.0], 0.25);
+    assert!((s - 1.0).abs() <= 0.125 && (t - 0.5).abs() <= 0.125);
+}
+
+/// Lumps of a closed box room, interior x and y in [-64, 64], z in [0, 128].
+///
+/// Face `i` lies on node `i`, the world nodes form a chain ending in the
+/// single empty leaf 1 and hull 1 is a similar chain of clip nodes.
+struct Room {
+    entities: String,
+    planes: Vec<Plane>,
+    textures: Vec<MipTextureHeader>,
+    vertices: Vec<Vec3f>,
+    visdata: Vec<u8>,
+    nodes: Vec<Node>,
+    texture_infos: Vec<TextureInfo>,
+    faces: Vec<Face>,
+    lighting: Vec<u8>,
+    clip_nodes: Vec<ClipNode>,
+    leaves: Vec<Leaf>,
+    mark_surfaces: Vec<U16>,
+    edges: Vec<Edge>,
+    surfedges: Vec<I32>,
+    models: Vec<Model>,
+}
+
+/// Luxels per lightmap of a room face (9 x 9).
+const ROOM_LIGHTMAP_SIZE: usize = 81;
+
+impl Room {
+    fn new() -> Self {
+        let vec3f = |v: [f32; 3]| v.map(F32::new);
+        let vec3s = |v: [i16; 3]| v.map(I16::new);
+        let room_bounds = BBox {
+            min: vec3s([-64, -64, 0]),
+            max: vec3s([64, 64, 128]),
+        };
+
+        // Axis, distance and whether the room is in front of the plane.
+        let walls = [
+            (2, 0.0, true),
+            (2, 128.0, false),
+            (0, -64.0, true),
+            (0, 64.0, false),
+            (1, -64.0, true),
+            (1, 64.0, false),
+        ];
+        let hull_walls = [36.0, 92.0, -48.0, 48.0, -48.0, 48.0];
+        let plane = |axis: usize, distance: f32| {
+            let mut normal = [0.0; 3];
+            normal[axis] = 1.0;
+            Plane {
+                normal: vec3f(normal),
+                distance: F32::new(distance),
+                ty: U32::new(axis as u32),
+            }
+        };
+        let planes = walls
+            .iter()
+            .map(|&(axis, distance, _)| plane(axis, distance))
+            .chain(
+                walls
+                    .iter()
+                    .zip(hull_walls)
+                    .map(|(&(axis, ..), distance)| plane(axis, distance)),
+            )
+            .collect();
+
+        // Chain of nodes, the side facing out of the room is solid.
+        let chain = |i: usize, front: bool, last: i16, solid: i16| {
+            let next = if i + 1 < walls.len() {
+                i as i16 + 1
+            } else {
+                last
+            };
+            if front { [next, solid] } else { [solid, next] }.map(I16::new)
+        };
+        let nodes = walls
+            .iter()
+            .enumerate()
+            .map(|(i, &(_, _, front))| Node {
+                plane_id: U32::new(i as u32),
+                children: chain(i, front, !1, !0),
+                bounds: room_bounds.clone(),
+                first_face_id: U16::new(i as u16),
+                faces_num: U16::new(1),
+            })
+            .collect();
+        let clip_nodes = walls
+            .iter()
+            .enumerate()
+            .map(|(i, &(_, _, front))| ClipNode {
+                plane_id: U32::new((walls.len() + i) as u32),
+                children: chain(i, front, CONTENTS_EMPTY as i16, CONTENTS_SOLID as i16),
+            })
+            .collect();
+
+        // Corner `i` has x, y and z set by bits 0, 1 and 2.
+        let vertices = (0..8)
+            .map(|i| {
+                let corner = |bit: usize, min: f32, max: f32| {
+                    if i & (1 << bit) != 0 { max } else { min }
+                };
+                vec3f([
+                    corner(0, -64.0, 64.0),
+                    corner(1, -64.0, 64.0),
+                    corner(2, 0.0, 128.0),
+                ])
+            })
+            .collect();
+        // Corners wound clockwise as seen from inside the room.
+        let windings: [[u16; 4]; 6] = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+        ];
+        let mut edges = vec![[U16::new(0); 2]];
+        let mut surfedges = Vec::new();
+        for winding in windings {
+            for (i, &v0) in winding.iter().enumerate() {
+                let v1 = winding[(i + 1) % winding.len()];
+                let shared = edges
+                    .iter()
+                    .position(|edge| edge[0].get() == v1 && edge[1].get() == v0);
+                surfedges.push(I32::new(match shared {
+                    Some(edge_id) => -(edge_id as i32),
+                    None => {
+                        edges.push([U16::new(v0), U16::new(v1)]);
+                        edges.len() as i32 - 1
+                    }
+                }));
+            }
+        }
+
+        let texture_axes = [
+            ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [0.0, 0.0, -1.0]),
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ];
+        let texture_infos = (0..walls.len())
+            .map(|i| {
+                let (s, t) = texture_axes[i / 2];
+                TextureInfo {
+                    s: vec3f(s),
+                    s_shift: F32::new(0.0),
+                    t: vec3f(t),
+                    t_shift: F32::new(0.0),
+                    texture_id: U32::new(i as u32),
+                    flags: U32::new(0),
+                }
+            })
+            .collect();
+        let names: [&[u8]; 6] = [b"floor", b"ceiling", b"west", b"east", b"south", b"north"];
+        let textures = names
+            .iter()
+            .map(|name| {
+                let mut header = MipTextureHeader {
+                    name: [0; 16],
+                    width: U32::new(64),
+                    height: U32::new(64),
+                    offsets: [U32::new(0); 4],
+                };
+                header.name[..name.len()].copy_from_slice(name);
+                header
+            })
+            .collect();
+
+        let faces = walls
+            .iter()
+            .enumerate()
+            .map(|(i, &(_, _, front))| Face {
+                plane_id: U16::new(i as u16),
+                plane_side: U16::new(u16::from(!front)),
+                first_surfedge_id: U32::new(i as u32 * 4),
+                surfedges_num: U16::new(4),
+                texture_info_id: U16::new(i as u16),
+                lighting_styles: [0, 255, 255, 255],
+                lightmap_offset: U32::new((i * ROOM_LIGHTMAP_SIZE * 3) as u32),
+            })
+            .collect();
+        let lighting = vec![128; walls.len() * ROOM_LIGHTMAP_SIZE * 3];
+
+        let leaves = vec![
+            Leaf {
+                contents: I32::new(CONTENTS_SOLID),
+                vis_offset: I32::new(-1),
+                bounds: BBox {
+                    min: vec3s([0; 3]),
+                    max: vec3s([0; 3]),
+                },
+                first_mark_surface_id: U16::new(0),
+                mark_surfaces_num: U16::new(0),
+                ambient_levels: [0; 4],
+            },
+            Leaf {
+                contents: I32::new(CONTENTS_EMPTY),
+                vis_offset: I32::new(0),
+                bounds: room_bounds,
+                first_mark_surface_id: U16::new(0),
+                mark_surfaces_num: U16::new(walls.len() as u16),
+                ambient_levels: [0; 4],
+            },
+        ];
+        let mark_surfaces = (0..walls.len() as u16).map(U16::new).collect();
+
+        let models = vec![Model {
+            bounds: BBox {
+                min: vec3f([-64.0, -64.0, 0.0]),
+                max: vec3f([64.0, 64.0, 128.0]),
+            },
+            origin: vec3f([0.0; 3]),
+            nodes: [I32::new(0); 4],
+            vis_leafs: I32::new(1),
+            first_face_id: U32::new(0),
+            faces_num: U32::new(walls.len() as u32),
+        }];
+
+        Self {
+            entities: "{\n\"classname\" \"worldspawn\"\n}\n".to_owned(),
+            planes,
+            textures,
+            vertices,
+            visdata: vec![0x01],
+            nodes,
+            texture_infos,
+            faces,
+            lighting,
+            clip_nodes,
+            leaves,
+            mark_surfaces,
+            edges,
+            surfedges,
+            models,
+        }
+    }
+
+    /// Room whose node and clip node chains loop back to their first node.
+    fn cyclic() -> Self {
+        let mut room = Self::new();
+        room.nodes[5].children[1] = I16::new(0);
+        room.clip_nodes[5].children[1] = I16::new(0);
+        room
+    }
+
+    /// Room without lightmaps, as compiled without RAD.
+    fn unlit() -> Self {
+        let mut room = Self::new();
+        for face in &mut room.faces {
+            face.lighting_styles = [255; 4];
+            face.lightmap_offset = U32::new(u32::MAX);
+        }
+        room.lighting.clear();
+        room
+    }
+
+    fn rename_texture(&mut self, texture_id: usize, name: &[u8]) {
+        let header = &mut self.textures[texture_id];
+        header.name = [0; 16];
+        header.name[..name.len()].copy_from_slice(name);
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut textures = U32::new(self.textures.len() as u32).as_bytes().to_vec();
+        for i in 0..self.textures.len() {
+            let offset = 4 + 4 * self.textures.len() + i * size_of::<MipTextureHeader>();
+            textures.extend_from_slice(U32::new(offset as u32).as_bytes());
+        }
+        textures.extend_from_slice(self.textures.as_bytes());
+        let mut entities = self.entities.as_bytes().to_vec();
+        entities.push(0);
+
+        let lumps: [&[u8]; 15] = [
+            &entities,
+            self.planes.as_bytes(),
+            &textures,
+            self.vertices.as_bytes(),
+            &self.visdata,
+            self.nodes.as_bytes(),
+            self.texture_infos.as_bytes(),
+            self.faces.as_bytes(),
+            &self.lighting,
+            self.clip_nodes.as_bytes(),
+            self.leaves.as_bytes(),
+            self.mark_surfaces.as_bytes(),
+            self.edges.as_bytes(),
+            self.surfedges.as_bytes(),
+            self.models.as_bytes(),
+        ];
+
+        let mut bytes = vec![0; size_of::<LevelHeader>()];
+        let mut header = LevelHeader {
+            version: U32::new(BSP_VERSION),
+            lumps: std::array::from_fn(|_| Lump {
+                offset: U32::new(0),
+                size: U32::new(0),
+            }),
+        };
+        for (lump, data) in header.lumps.iter_mut().zip(lumps) {
+            bytes.resize(bytes.len().next_multiple_of(4), 0);
+            lump.offset = U32::new(bytes.len() as u32);
+            lump.size = U32::new(data.len() as u32);
+            bytes.extend_from_slice(data);
+        }
+        bytes[..size_of::<LevelHeader>()].copy_from_slice(header.as_bytes());
+        bytes
+    }
+}
@@ -0,0 +1,51 @@
+use goldsrc_rs::{
+    bsp::level,
+    detail::{detail_textures, level_detail_textures},
+};
+
+#[test]
+fn parse_detail() {
+    for path in glob::glob("./valve/maps/*_detail.txt")
+        .expect("error globing detail")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let details = detail_textures(&data).unwrap();
+
+        println!("Detail textures: {}", details.len());
+
+        let bsp_path = path
+            .to_str()
+            .and_then(|path| path.strip_suffix("_detail.txt"))
+            .map(|path| format!("{path}.bsp"));
+        if let Some(Ok(bsp)) = bsp_path.map(std::fs::read) {
+            let level = level(&bsp).unwrap();
+            let mapped = level_detail_textures(&level, &details);
+            assert_eq!(mapped.len(), level.textures.len());
+            println!("Detailed: {}", mapped.iter().flatten().count());
+        }
+    }
+}
+
+#[test]
+fn parse_synthetic_detail() {
+    let data = b"// generated by hand\r\n\
+        floor detail/metal1 2 2.5\r\n\
+        \r\n\
+        FLOOR detail/rock 1 1 // listed twice\n\
+        +0fan detail/grate 4.0 4.0";
+    let details = detail_textures(data).unwrap();
+
+    assert_eq!(details.len(), 3);
+    assert_eq!(details[0].texture, b"floor");
+    assert_eq!(details[0].detail, b"detail/metal1");
+    assert_eq!(details[0].scale, [2.0, 2.5]);
+    assert_eq!(details[0].path(), "gfx/detail/metal1.tga");
+    assert_eq!(details[1].texture, b"FLOOR");
+    assert_eq!(details[2].texture, b"+0fan");
+    assert_eq!(details[2].scale, [4.0, 4.0]);
+
+    assert!(detail_textures(b"floor detail/metal1 2\n").is_err());
+    assert!(detail_textures(b"floor detail/metal1 2 x\n").is_err());
+}
@@ -1,9 +1,32 @@
 use goldsrc_rs::{
-    common::cstring_bytes,
-    mdl::{mdl, texture_data},
+    common::{Table, Vec3f, cstring_bytes},
+    error::ParsingError,
+    mdl::{
+        BodyPart, Bone, BoneController, MDL_MAGIC, MDL_VERSION, MdlHeader, Mesh, Model, STUDIO_X,
+        STUDIO_XR, STUDIO_ZR, SequenceDesc, SequenceGroup, Texture,
+        anim::sequence_anim,
+        blend::{blend_poses, blend_setting, blended_pose, crossfade_weight},
+        body::{body_models, encode_body, set_bodygroup, skin_texture},
+        bodypart_models,
+        controller::{Controllers, bone_adjustments},
+        mdl, model_meshes,
+        pose::{angle_quaternion, bone_matrices, pose},
+        seqgroup::{
+            SEQUENCE_GROUP_MAGIC, SequenceGroupHeader, sequence_anim_bytes, sequence_group,
+            sequence_group_path,
+        },
+        skinning::{skin_normals, skin_vertices, skinned_bbox, skinned_body},
+        texmodel::{ModelFiles, texture_model_path},
+        texture_data,
+        tricmd::mesh_triangles,
+    },
 };
 
 use std::path::Path;
+use zerocopy::{
+    FromZeros, IntoBytes,
+    little_endian::{F32, I16, I32, U16, U32},
+};
 
 mod common;
 
@@ -73,3 +96,957 @@ fn parse_mdl() {
         }
     }
 }
+
+#[test]
+fn mdl_triangles() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let data = std::fs::read(&path).expect("error reading file");
+        let Ok(model) = mdl(&data) else {
+            continue;
+        };
+        println!("File: {:?}", path);
+
+        let mut triangles_num = 0;
+        for bodypart in model.bodyparts {
+            for submodel in bodypart_models(&data, bodypart).unwrap() {
+                for mesh in model_meshes(&data, submodel).unwrap() {
+                    triangles_num += mesh_triangles(&data, submodel, mesh).unwrap().len();
+                }
+            }
+        }
+        println!("Triangles: {triangles_num}");
+    }
+}
+
+#[test]
+fn triangles_studio() {
+    let data = Studio::new().bytes();
+    let model = mdl(&data).unwrap();
+    let submodel = &bodypart_models(&data, &model.bodyparts[0]).unwrap()[0];
+    let mesh = &model_meshes(&data, submodel).unwrap()[0];
+
+    let triangles = mesh_triangles(&data, submodel, mesh).unwrap();
+    let vertex_ids: Vec<_> = triangles
+        .iter()
+        .map(|triangle| triangle.map(|vertex| vertex.vertex_id))
+        .collect();
+    // One strip of two triangles then one fan.
+    assert_eq!(vertex_ids, [[0, 1, 2], [2, 1, 3], [0, 2, 3]]);
+    assert_eq!(triangles[1][2].tex_coords, [4, 4]);
+    assert!(
+        triangles
+            .iter()
+            .flatten()
+            .all(|vertex| vertex.normal_id == 0)
+    );
+
+    let mut broken = Studio::new();
+    broken.tricmds[1] = I16::new(4);
+    let data = broken.bytes();
+    let model = mdl(&data).unwrap();
+    let submodel = &bodypart_models(&data, &model.bodyparts[0]).unwrap()[0];
+    let mesh = &model_meshes(&data, submodel).unwrap()[0];
+    assert!(mesh_triangles(&data, submodel, mesh).is_err());
+}
+
+#[test]
+fn mdl_anims() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let data = std::fs::read(&path).expect("error reading file");
+        let Ok(model) = mdl(&data) else {
+            continue;
+        };
+        println!("File: {:?}", path);
+
+        for sequence in model.sequences {
+            if sequence.sequence_group.get() != 0 {
+                continue;
+            }
+            for blend in 0..sequence.blends_num.get() as usize {
+                let anim = sequence_anim(&data, model.bones.len(), sequence, blend).unwrap();
+                for frame in 0..anim.frames_num {
+                    for (bone_id, bone) in model.bones.iter().enumerate() {
+                        anim.values(bone, bone_id, frame).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn anims_studio() {
+    let data = Studio::new().bytes();
+    let model = mdl(&data).unwrap();
+    let sequence = &model.sequences[0];
+    let [root, child] = model.bones else {
+        panic!("expected two bones");
+    };
+
+    let anim = sequence_anim(&data, 2, sequence, 0).unwrap();
+    assert_eq!(anim.frames_num, 3);
+    let xs: Vec<_> = (0..4)
+        .map(|frame| anim.value(root, 0, 0, frame).unwrap())
+        .collect();
+    // Frames past the last one are clamped.
+    assert_eq!(xs, [0.0, 10.0, 20.0, 20.0]);
+    assert_eq!(anim.raw_value(0, 1, 0).unwrap(), None);
+    // One valid value held over the three frames.
+    for frame in 0..3 {
+        assert_eq!(anim.raw_value(1, 5, frame).unwrap(), Some(100));
+    }
+    assert_eq!(
+        anim.values(child, 1, 2).unwrap(),
+        [10.0, 0.0, 0.0, 0.0, 0.0, 1.0]
+    );
+    assert!(anim.raw_value(2, 0, 0).is_err());
+
+    let anim = sequence_anim(&data, 2, sequence, 1).unwrap();
+    assert_eq!(anim.value(root, 0, 0, 1).unwrap(), 50.0);
+    assert!(sequence_anim(&data, 2, sequence, 2).is_err());
+}
+
+#[test]
+fn mdl_poses() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let data = std::fs::read(&path).expect("error reading file");
+        let Ok(model) = mdl(&data) else {
+            continue;
+        };
+        println!("File: {:?}", path);
+
+        for sequence in model.sequences {
+            if sequence.sequence_group.get() != 0 {
+                continue;
+            }
+            let anim = sequence_anim(&data, model.bones.len(), sequence, 0).unwrap();
+            for frame in 0..anim.frames_num * 2 {
+                let pose = pose(&anim, model.bones, sequence, frame as f32 * 0.5, &[]).unwrap();
+                let matrices = bone_matrices(model.bones, &pose).unwrap();
+                assert!(matrices.iter().flatten().flatten().all(|v| v.is_finite()));
+            }
+        }
+    }
+}
+
+#[test]
+fn poses_studio() {
+    let mut studio = Studio::new();
+    let data = studio.bytes();
+    let model = mdl(&data).unwrap();
+    let sequence = &model.sequences[0];
+    let anim = sequence_anim(&data, 2, sequence, 0).unwrap();
+
+    // Fractional frames interpolate, frames past the end are clamped.
+    for (frame, x) in [(0.5, 5.0), (1.5, 15.0), (9.0, 20.0)] {
+        let frame_pose = pose(&anim, model.bones, sequence, frame, &[]).unwrap();
+        assert_eq!(frame_pose[0].position, [x, 0.0, 0.0]);
+    }
+
+    let first = pose(&anim, model.bones, sequence, 0.0, &[]).unwrap();
+    let [sin, cos] = [1.0f32.sin(), 1.0f32.cos()];
+    let rotation = first[1].rotation;
+    assert!((rotation[2] - 0.5f32.sin()).abs() < 1e-6);
+    assert!((rotation[3] - 0.5f32.cos()).abs() < 1e-6);
+    let matrices = bone_matrices(model.bones, &first).unwrap();
+    let child = matrices[1];
+    for (row, expected) in child
+        .iter()
+        .zip([[cos, -sin, 0.0, 10.0], [sin, cos, 0.0, 0.0]])
+    {
+        assert!(row.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+    assert!(bone_matrices(&model.bones[..1], &first).is_err());
+    assert!(pose(&anim, &model.bones[..1], sequence, 0.0, &[]).is_err());
+
+    // Movement along the motion axes is left to the entity.
+    studio.sequence.motion_type = I32::new(STUDIO_X);
+    studio.sequence.motion_bone = I32::new(0);
+    let data = studio.bytes();
+    let model = mdl(&data).unwrap();
+    let sequence = &model.sequences[0];
+    let anim = sequence_anim(&data, 2, sequence, 0).unwrap();
+    let last = pose(&anim, model.bones, sequence, 2.0, &[]).unwrap();
+    assert_eq!(last[0].position, [0.0; 3]);
+    assert_eq!(last[1].position, [10.0, 0.0, 0.0]);
+}
+
+#[test]
+fn mdl_controllers() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let data = std::fs::read(&path).expect("error reading file");
+        let Ok(model) = mdl(&data) else {
+            continue;
+        };
+        let Some(sequence) = model.sequences.first() else {
+            continue;
+        };
+        if sequence.sequence_group.get() != 0 {
+            continue;
+        }
+        println!("File: {:?}", path);
+
+        let mut controllers = Controllers::rest(model.bone_controllers);
+        for controller in model.bone_controllers {
+            let index = controller.index.get() as usize;
+            let start = controller.start.get();
+            let end = controller.end.get();
+            let value = controllers
+                .set(model.bone_controllers, index, (start + end) / 2.0)
+                .unwrap();
+            println!("Controller {index}: {value}");
+        }
+
+        let adjustments = bone_adjustments(model.bone_controllers, &controllers);
+        assert_eq!(adjustments.len(), model.bone_controllers.len());
+        let anim = sequence_anim(&data, model.bones.len(), sequence, 0).unwrap();
+        pose(&anim, model.bones, sequence, 0.0, &adjustments).unwrap();
+    }
+}
+
+#[test]
+fn controllers_studio() {
+    let data = Studio::new().bytes();
+    let model = mdl(&data).unwrap();
+    let bone_controllers = model.bone_controllers;
+
+    let mut controllers = Controllers::rest(bone_controllers);
+    assert_eq!(controllers, Controllers::default());
+    let value = controllers.set(bone_controllers, 0, 45.0).unwrap();
+    assert_eq!(controllers.controllers[0], 191);
+    assert!((value - 44.82353).abs() < 1e-4);
+    // Rotations wrap towards the controller range.
+    assert_eq!(controllers.set(bone_controllers, 0, 405.0), Some(value));
+    assert_eq!(controllers.set(bone_controllers, 4, 15.0), Some(15.0));
+    assert_eq!(controllers.mouth, 32);
+    assert_eq!(controllers.set(bone_controllers, 1, 10.0), None);
+
+    let adjustments = bone_adjustments(bone_controllers, &controllers);
+    assert!((adjustments[0] - value.to_radians()).abs() < 1e-6);
+    assert!((adjustments[1] - 15f32.to_radians()).abs() < 1e-6);
+
+    let sequence = &model.sequences[0];
+    let anim = sequence_anim(&data, 2, sequence, 0).unwrap();
+    let adjusted = pose(&anim, model.bones, sequence, 0.0, &adjustments).unwrap();
+    let expected = angle_quaternion([adjustments[1], 0.0, 1.0 + adjustments[0]]);
+    assert!(
+        adjusted[1]
+            .rotation
+            .iter()
+            .zip(expected)
+            .all(|(a, b)| (a - b).abs() < 1e-6)
+    );
+    assert_eq!(adjusted[0].rotation, [0.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn mdl_blends() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let data = std::fs::read(&path).expect("error reading file");
+        let Ok(model) = mdl(&data) else {
+            continue;
+        };
+        println!("File: {:?}", path);
+
+        let mut previous = None;
+        for sequence in model.sequences {
+            if sequence.sequence_group.get() != 0 {
+                continue;
+            }
+            let blending = [0, 1].map(|blender| {
+                let start = sequence.blend_start[blender].get();
+                let end = sequence.blend_end[blender].get();
+                blend_setting(sequence, blender, (start + end) / 2.0).unwrap_or(0)
+            });
+            let pose = blended_pose(&data, model.bones, sequence, 0.0, blending, &[]).unwrap();
+            if let Some(previous) = previous.replace(pose.clone()) {
+                blend_poses(&pose, &previous, crossfade_weight(0.1)).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn blends_studio() {
+    let data = Studio::new().bytes();
+    let model = mdl(&data).unwrap();
+    let sequence = &model.sequences[0];
+
+    assert_eq!(blend_setting(sequence, 0, -45.0), Some(0));
+    assert_eq!(blend_setting(sequence, 0, 0.0), Some(127));
+    assert_eq!(blend_setting(sequence, 0, 45.0), Some(255));
+    assert_eq!(blend_setting(sequence, 1, 0.0), None);
+    assert_eq!(blend_setting(sequence, 2, 0.0), None);
+
+    // Blend 0 moves the root to 10 at frame 1, blend 1 holds it at 50.
+    for (setting, x) in [(0, 10.0), (127, 10.0 + 40.0 * 127.0 / 255.0), (255, 50.0)] {
+        let blended = blended_pose(&data, model.bones, sequence, 1.0, [setting, 0], &[]).unwrap();
+        assert!((blended[0].position[0] - x).abs() < 1e-4);
+    }
+
+    let from = blended_pose(&data, model.bones, sequence, 0.0, [0, 0], &[]).unwrap();
+    let to = blended_pose(&data, model.bones, sequence, 0.0, [255, 0], &[]).unwrap();
+    let half = blend_poses(&from, &to, 0.5).unwrap();
+    assert_eq!(half[0].position, [25.0, 0.0, 0.0]);
+    assert_eq!(blend_poses(&from, &to, 2.0).unwrap(), to);
+    assert!(blend_poses(&from, &to[..1], 0.5).is_err());
+
+    assert_eq!(crossfade_weight(0.0), 1.0);
+    assert_eq!(crossfade_weight(0.05), 0.75);
+    assert_eq!(crossfade_weight(1.0), 0.0);
+}
+
+#[test]
+fn mdl_sequence_groups() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let data = std::fs::read(&path).expect("error reading file");
+        let Ok(model) = mdl(&data) else {
+            continue;
+        };
+        if model.sequence_groups.len() < 2 {
+            continue;
+        }
+        println!("File: {:?}", path);
+
+        let group_data: Vec<_> = (1..model.sequence_groups.len())
+            .map(|group| {
+                std::fs::read(sequence_group_path(&path, group)).expect("error reading group")
+            })
+            .collect();
+        let group_files: Vec<_> = group_data.iter().map(Vec::as_slice).collect();
+        for bytes in &group_files {
+            let header = sequence_group(bytes).unwrap();
+            println!(
+                "Group: {}",
+                String::from_utf8_lossy(cstring_bytes(&header.name))
+            );
+        }
+
+        for sequence in model.sequences {
+            let bytes = sequence_anim_bytes(&data, &group_files, sequence).unwrap();
+            let anim = sequence_anim(bytes, model.bones.len(), sequence, 0).unwrap();
+            pose(&anim, model.bones, sequence, 0.0, &[]).unwrap();
+        }
+    }
+}
+
+#[test]
+fn sequence_groups_studio() {
+    let data = Studio::new().bytes();
+    let model = mdl(&data).unwrap();
+    let sequence = &model.sequences[0];
+    assert_eq!(
+        sequence_anim_bytes(&data, &[], sequence).unwrap(),
+        &data[..]
+    );
+
+    let mut studio = Studio::new();
+    studio.grouped = true;
+    let data = studio.bytes();
+    let group_file = studio.group_file();
+    let model = mdl(&data).unwrap();
+    let sequence = &model.sequences[0];
+    assert_eq!(model.sequence_groups.len(), 2);
+
+    let header = sequence_group(&group_file).unwrap();
+    assert_eq!(cstring_bytes(&header.name), b"test01.mdl");
+    assert_eq!(header.length.get() as usize, group_file.len());
+
+    let bytes = sequence_anim_bytes(&data, &[&group_file], sequence).unwrap();
+    assert_eq!(bytes, &group_file[..]);
+    let anim = sequence_anim(bytes, 2, sequence, 0).unwrap();
+    assert_eq!(anim.value(&model.bones[0], 0, 0, 1).unwrap(), 10.0);
+    assert!(sequence_anim_bytes(&data, &[], sequence).is_err());
+
+    let mut wrong_magic = group_file.clone();
+    wrong_magic[..4].copy_from_slice(b"IDST");
+    assert!(sequence_group(&wrong_magic).is_err());
+    assert!(sequence_anim_bytes(&data, &[&wrong_magic], sequence).is_err());
+    let mut wrong_version = group_file.clone();
+    wrong_version[4] = 6;
+    assert!(sequence_group(&wrong_version).is_err());
+    assert!(sequence_group(&group_file[..8]).is_err());
+
+    assert_eq!(
+        sequence_group_path(Path::new("models/test.mdl"), 1),
+        Path::new("models/test01.mdl")
+    );
+}
+
+#[test]
+fn mdl_texture_models() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let path = path.to_string_lossy();
+        let Ok(files) = ModelFiles::load(&path, |path| std::fs::read(path).ok()) else {
+            continue;
+        };
+        if files.textures.is_none() {
+            continue;
+        }
+        println!("File: {:?}", path);
+
+        let model = files.model().unwrap();
+        for texture in model.textures {
+            files.texture_data(texture).unwrap();
+        }
+    }
+}
+
+#[test]
+fn texture_models_studio() {
+    let mut studio = Studio::new();
+    let texture_model = studio.bytes();
+    studio.textures.clear();
+    studio.skins.clear();
+    let model = studio.bytes();
+
+    let mut loaded = Vec::new();
+    let files = ModelFiles::load("models/test.mdl", |path| {
+        loaded.push(path.to_owned());
+        match path {
+            "models/test.mdl" => Some(model.clone()),
+            "models/testT.mdl" => Some(texture_model.clone()),
+            _ => None,
+        }
+    })
+    .unwrap();
+    assert_eq!(loaded, ["models/test.mdl", "models/testT.mdl"]);
+    assert_eq!(files.texture_bytes(), &texture_model[..]);
+
+    let merged = files.model().unwrap();
+    assert_eq!(merged.bones.len(), 2);
+    assert_eq!(merged.textures.len(), 1);
+    assert_eq!(merged.skins.skin_families_num, 2);
+    let data = files.texture_data(&merged.textures[0]).unwrap();
+    assert_eq!(data.indices[0][..5], [0, 1, 2, 3, 0]);
+    assert_eq!(data.palette[2], [2, 2, 2]);
+
+    // A model without textures needs its texture model.
+    let missing = ModelFiles::load("models/test.mdl", |path| {
+        (path == "models/test.mdl").then(|| model.clone())
+    });
+    assert!(missing.is_err());
+    let files = ModelFiles::load("models/test.mdl", |path| {
+        assert_eq!(path, "models/test.mdl");
+        Some(texture_model.clone())
+    })
+    .unwrap();
+    assert!(files.textures.is_none());
+
+    assert_eq!(texture_model_path("models/Test.MDL"), "models/TestT.mdl");
+    assert_eq!(texture_model_path("models/test"), "models/testT.mdl");
+}
+
+#[test]
+fn mdl_skinning() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let path = path.to_string_lossy();
+        let Ok(files) = ModelFiles::load(&path, |path| std::fs::read(path).ok()) else {
+            continue;
+        };
+        let model = files.model().unwrap();
+        let Some(sequence) = model.sequences.first() else {
+            continue;
+        };
+        if sequence.sequence_group.get() != 0 {
+            continue;
+        }
+        println!("File: {:?}", path);
+
+        let anim = sequence_anim(&files.model, model.bones.len(), sequence, 0).unwrap();
+        let pose = pose(&anim, model.bones, sequence, 0.0, &[]).unwrap();
+        let matrices = bone_matrices(model.bones, &pose).unwrap();
+        let meshes = skinned_body(&files.model, &model, &matrices, 0, 0).unwrap();
+        if let Some(bbox) = skinned_bbox(&meshes) {
+            println!("Bounds: {:?} {:?}", bbox.min, bbox.max);
+        }
+    }
+}
+
+#[test]
+fn skinning_studio() {
+    let mut studio = Studio::new();
+    let data = studio.bytes();
+    let model = mdl(&data).unwrap();
+    let sequence = &model.sequences[0];
+    let anim = sequence_anim(&data, 2, sequence, 0).unwrap();
+    let matrices = bone_matrices(
+        model.bones,
+        &pose(&anim, model.bones, sequence, 0.0, &[]).unwrap(),
+    )
+    .unwrap();
+
+    let submodel = &bodypart_models(&data, &model.bodyparts[0]).unwrap()[0];
+    let positions = skin_vertices(&data, submodel, &matrices).unwrap();
+    // The last two vertices follow the child bone, 10 units along X and
+    // rotated by 1 radian around Z.
+    let [sin, cos] = [1.0f32.sin(), 1.0f32.cos()];
+    let expected = [
+        [0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [10.0, 0.0, 1.0],
+        [10.0 - sin, cos, 1.0],
+    ];
+    for (position, expected) in positions.iter().zip(expected) {
+        assert!(
+            position
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-5)
+        );
+    }
+    assert_eq!(
+        skin_normals(&data, submodel, &matrices).unwrap(),
+        [[1.0, 0.0, 0.0]]
+    );
+    assert!(skin_vertices(&data, submodel, &matrices[..1]).is_err());
+
+    let meshes = skinned_body(&data, &model, &matrices, 0, 0).unwrap();
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(meshes[0].texture_id, 0);
+    assert_eq!(meshes[0].vertices.len(), 9);
+    let corner = meshes[0].vertices[5];
+    assert_eq!(corner.position, positions[3]);
+    assert_eq!(corner.tex_coords, [1.0, 1.0]);
+
+    let bbox = skinned_bbox(&meshes).unwrap();
+    assert_eq!(bbox.min, [0.0, 0.0, 0.0]);
+    assert!((bbox.max[0] - 10.0).abs() < 1e-5 && bbox.max[1] == 1.0 && bbox.max[2] == 1.0);
+    assert!(skinned_bbox(&[]).is_none());
+
+    studio.textures[0].width = U32::new(0);
+    let data = studio.bytes();
+    let model = mdl(&data).unwrap();
+    assert!(matches!(
+        skinned_body(&data, &model, &matrices, 0, 0),
+        Err(ParsingError::Invalid("mdl texture size"))
+    ));
+}
+
+#[test]
+fn mdl_bodygroups() {
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let path = path.to_string_lossy();
+        let Ok(files) = ModelFiles::load(&path, |path| std::fs::read(path).ok()) else {
+            continue;
+        };
+        let model = files.model().unwrap();
+        println!("File: {:?}", path);
+
+        let mut body = 0;
+        for (bodypart_id, bodypart) in model.bodyparts.iter().enumerate() {
+            let Some(last) = (bodypart.models_num.get() as usize).checked_sub(1) else {
+                continue;
+            };
+            body = set_bodygroup(model.bodyparts, body, bodypart_id, last).unwrap();
+            assert_eq!(
+                body_models(model.bodyparts, body).unwrap()[bodypart_id],
+                last
+            );
+        }
+        let models = body_models(model.bodyparts, body).unwrap();
+        assert_eq!(encode_body(model.bodyparts, &models).unwrap(), body);
+
+        for skin in 0..model.skins.skin_families_num as usize {
+            for bodypart in model.bodyparts {
+                for submodel in bodypart_models(&files.model, bodypart).unwrap() {
+                    for mesh in model_meshes(&files.model, submodel).unwrap() {
+                        skin_texture(&model, skin, mesh.skin_ref.get()).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn bodygroups_studio() {
+    let mut studio = Studio::new();
+    studio.bodyparts = vec![2, 3];
+    let mut alternate = studio.textures[0].clone();
+    alternate.name = studio_name("alt.bmp");
+    studio.textures.push(alternate);
+    studio.skins = [0, 1].map(U16::new).to_vec();
+    let data = studio.bytes();
+    let model = mdl(&data).unwrap();
+    let bodyparts = model.bodyparts;
+
+    assert_eq!(bodyparts[1].base.get(), 2);
+    assert_eq!(body_models(bodyparts, 0).unwrap(), [0, 0]);
+    assert_eq!(body_models(bodyparts, 5).unwrap(), [1, 2]);
+    assert_eq!(encode_body(bodyparts, &[1, 2]).unwrap(), 5);
+    assert!(encode_body(bodyparts, &[0, 3]).is_err());
+    assert!(encode_body(bodyparts, &[0]).is_err());
+    assert_eq!(set_bodygroup(bodyparts, 5, 1, 0).unwrap(), 1);
+    assert_eq!(set_bodygroup(bodyparts, 0, 1, 2).unwrap(), 4);
+    assert!(set_bodygroup(bodyparts, 0, 2, 0).is_err());
+
+    let meshes = skinned_body(&data, &model, &[[[0.0; 4]; 3]; 2], 5, 1).unwrap();
+    assert_eq!(meshes.len(), 2);
+    assert!(meshes.iter().all(|mesh| mesh.texture_id == 1));
+
+    assert_eq!(skin_texture(&model, 0, 0).unwrap(), 0);
+    assert_eq!(skin_texture(&model, 1, 0).unwrap(), 1);
+    // Like the engine, out of range families use family 0.
+    assert_eq!(skin_texture(&model, 2, 0).unwrap(), 0);
+    assert!(skin_texture(&model, 0, 1).is_err());
+    assert!(skin_texture(&model, 0, -1).is_err());
+
+    studio.bodyparts = vec![0];
+    let data = studio.bytes();
+    let model = mdl(&data).unwrap();
+    assert!(body_models(model.bodyparts, 0).is_err());
+}
+
+#[cfg(feature = "gltf")]
+#[test]
+fn mdl_gltf() {
+    use goldsrc_rs::mdl::gltf::model_glb;
+
+    let out_dir = Path::new("./output");
+    std::fs::create_dir_all(out_dir).expect("error creating output dir");
+
+    for path in glob::glob("./valve/models/**/*.mdl")
+        .expect("error globing mdl")
+        .flatten()
+    {
+        let name = path.to_string_lossy();
+        let Ok(files) = ModelFiles::load(&name, |path| std::fs::read(path).ok()) else {
+            continue;
+        };
+        let model = files.model().unwrap();
+        println!("File: {:?}", path);
+
+        let group_data: Vec<_> = (1..model.sequence_groups.len())
+            .map_while(|group| std::fs::read(sequence_group_path(&path, group)).ok())
+            .collect();
+        let group_files: Vec<_> = group_data.iter().map(Vec::as_slice).collect();
+        let glb = model_glb(&files, &group_files).unwrap();
+
+        let stem = path.file_stem().unwrap().to_string_lossy();
+        std::fs::write(out_dir.join(format!("{stem}.glb")), glb).expect("error writing glb");
+    }
+}
+
+#[cfg(feature = "gltf")]
+#[test]
+fn gltf_studio() {
+    use goldsrc_rs::mdl::gltf::{GLB_MAGIC, GLB_VERSION, model_glb};
+
+    let u32_at = |glb: &[u8], offset: usize| {
+        u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap()) as usize
+    };
+    let files = ModelFiles {
+        model: Studio::new().bytes(),
+        textures: None,
+    };
+    let glb = model_glb(&files, &[]).unwrap();
+
+    assert_eq!(glb[..4], GLB_MAGIC);
+    assert_eq!(u32_at(&glb, 4), GLB_VERSION as usize);
+    assert_eq!(u32_at(&glb, 8), glb.len());
+
+    let json_len = u32_at(&glb, 12);
+    assert_eq!(json_len % 4, 0);
+    assert_eq!(&glb[16..20], b"JSON");
+    let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+    let bin = 20 + json_len;
+    let bin_len = u32_at(&glb, bin);
+    assert_eq!(bin_len % 4, 0);
+    assert_eq!(&glb[bin + 4..bin + 8], b"BIN\0");
+    assert_eq!(bin + 8 + bin_len, glb.len());
+
+    assert!(json.trim_end().starts_with('{') && json.trim_end().ends_with('}'));
+    assert!(json.contains(&format!(r#""buffers":[{{"byteLength":{bin_len}}}]"#)));
+    assert!(json.contains(r#""name":"part0/body_ref""#));
+    assert!(json.contains(r#""mimeType":"image/png""#));
+    assert!(json.contains(r#""name":"idle""#));
+    assert!(json.contains(r#""skins":["#) && json.contains(r#""JOINTS_0":"#));
+
+    // Sequences in group files that weren't passed are skipped.
+    let mut studio = Studio::new();
+    studio.grouped = true;
+    let files = ModelFiles {
+        model: studio.bytes(),
+        textures: None,
+    };
+    let glb = model_glb(&files, &[]).unwrap();
+    let json_len = u32_at(&glb, 12);
+    let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+    assert!(!json.contains(r#""animations""#));
+    let group_file = studio.group_file();
+    let glb = model_glb(&files, &[&group_file]).unwrap();
+    let json_len = u32_at(&glb, 12);
+    let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+    assert!(json.contains(r#""animations""#));
+}
+
+/// Two-bone model with one sequence, one texture and a quad made of three triangles.
+struct Studio {
+    bones: Vec<Bone>,
+    bone_controllers: Vec<BoneController>,
+    sequence: SequenceDesc,
+    anim: Vec<u8>,
+    /// Whether the animations live in a sequence group file (see `group_file`).
+    grouped: bool,
+    textures: Vec<Texture>,
+    skins: Vec<U16>,
+    skin_families_num: u32,
+    vertices: Vec<Vec3f>,
+    vertex_bones: Vec<u8>,
+    normals: Vec<Vec3f>,
+    normal_bones: Vec<u8>,
+    tricmds: Vec<I16>,
+    bodyparts: Vec<u32>,
+}
+
+impl Studio {
+    fn new() -> Self {
+        let vec3f = |v: [f32; 3]| v.map(F32::new);
+        let bone = |name, parent, bone_controller: [i32; 6], value: [f32; 6]| Bone {
+            name: studio_name(name),
+            parent: I32::new(parent),
+            flags: I32::new(0),
+            bone_controller: bone_controller.map(I32::new),
+            value: value.map(F32::new),
+            scale: [1.0, 1.0, 1.0, 0.01, 0.01, 0.01].map(F32::new),
+        };
+        let controller = |ty, start, end, index| BoneController {
+            bone: I32::new(1),
+            ty: I32::new(ty),
+            start: F32::new(start),
+            end: F32::new(end),
+            rest: I32::new(0),
+            index: I32::new(index),
+        };
+
+        let mut sequence = SequenceDesc::new_zeroed();
+        sequence.label = studio_name("idle");
+        sequence.fps = F32::new(10.0);
+        sequence.frames_num = I32::new(3);
+        sequence.blends_num = I32::new(2);
+        sequence.blend_type = [STUDIO_XR, 0].map(I32::new);
+        sequence.blend_start = [-45.0, 0.0].map(F32::new);
+        sequence.blend_end = [45.0, 0.0].map(F32::new);
+
+        // Offsets of each bone channel, relative to the bone entry, for two
+        // blends of two bones, then the value streams (valid, total, values).
+        let offsets: [[u16; 6]; 4] = [
+            [48, 0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0, 44],
+            [36, 0, 0, 0, 0, 0],
+            [0; 6],
+        ];
+        let mut anim = offsets
+            .map(|offsets| offsets.map(U16::new))
+            .as_bytes()
+            .to_vec();
+        for (header, values) in [
+            ([3, 3], &[0, 10, 20][..]),
+            ([1, 3], &[100]),
+            ([1, 3], &[50]),
+        ] {
+            anim.extend_from_slice(&header);
+            anim.extend(values.iter().flat_map(|&value: &i16| value.to_le_bytes()));
+        }
+
+        Self {
+            // The child bone is rotated around Z by controller 0 and around X
+            // by the mouth.
+            bones: vec![
+                bone("root", -1, [-1; 6], [0.0; 6]),
+                bone(
+                    "child",
+                    0,
+                    [-1, -1, -1, 1, -1, 0],
+                    [10.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                ),
+            ],
+            bone_controllers: vec![
+                controller(STUDIO_ZR, -90.0, 90.0, 0),
+                controller(STUDIO_XR, 0.0, 30.0, 4),
+            ],
+            sequence,
+            anim,
+            grouped: false,
+            textures: vec![Texture {
+                name: studio_name("skin.bmp"),
+                flags: U32::new(0),
+                width: U32::new(4),
+                height: U32::new(4),
+                offset: U32::new(0),
+            }],
+            skins: vec![U16::new(0); 2],
+            skin_families_num: 2,
+            vertices: [
+                [0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, 1.0, 1.0],
+            ]
+            .map(vec3f)
+            .to_vec(),
+            vertex_bones: vec![0, 0, 1, 1],
+            normals: vec![vec3f([1.0, 0.0, 0.0])],
+            normal_bones: vec![0],
+            // A strip of 4 vertices, a fan of 3, then the end of the list.
+            tricmds: [
+                4, 0, 0, 0, 0, 1, 0, 4, 0, 2, 0, 0, 4, 3, 0, 4, 4, -3, 0, 0, 0, 0, 2, 0, 0, 4, 3,
+                0, 4, 4, 0,
+            ]
+            .map(I16::new)
+            .to_vec(),
+            bodyparts: vec![1],
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; size_of::<MdlHeader>()];
+        let mut push = |data: &[u8]| {
+            bytes.resize(bytes.len().next_multiple_of(4), 0);
+            let offset = U32::new(bytes.len() as u32);
+            bytes.extend_from_slice(data);
+            offset
+        };
+        let table = |count: usize, offset: U32| Table {
+            count: U32::new(count as u32),
+            offset,
+        };
+
+        let mut header = MdlHeader::new_zeroed();
+        header.magic = MDL_MAGIC;
+        header.version = U32::new(MDL_VERSION);
+        header.name = studio_name("test.mdl");
+        header.bones = table(self.bones.len(), push(self.bones.as_bytes()));
+        header.bone_controllers = table(
+            self.bone_controllers.len(),
+            push(self.bone_controllers.as_bytes()),
+        );
+
+        let mut sequence = self.sequence.clone();
+        let mut groups = vec![SequenceGroup {
+            label: studio_name("default"),
+            ..SequenceGroup::new_zeroed()
+        }];
+        if self.grouped {
+            sequence.sequence_group = I32::new(1);
+            sequence.anim_index = I32::new(size_of::<SequenceGroupHeader>() as i32);
+            groups.push(SequenceGroup {
+                label: studio_name("anims"),
+                name: studio_name("test01.mdl"),
+                ..SequenceGroup::new_zeroed()
+            });
+        } else {
+            sequence.anim_index = I32::new(push(&self.anim).get() as i32);
+        }
+        header.sequences = table(1, push(sequence.as_bytes()));
+        header.sequence_groups = table(groups.len(), push(groups.as_bytes()));
+
+        let mut textures = self.textures.clone();
+        for texture in &mut textures {
+            let size = (texture.width.get() * texture.height.get()) as usize;
+            let mut data: Vec<u8> = (0..size).map(|i| (i % 4) as u8).collect();
+            data.extend((0..=255).flat_map(|i| [i; 3]));
+            texture.offset = push(&data);
+        }
+        header.texture_data_offset = textures
+            .first()
+            .map_or(U32::new(0), |texture| texture.offset);
+        header.textures = table(textures.len(), push(textures.as_bytes()));
+        header.skin_refs_num = U32::new(
+            (self.skins.len())
+                .checked_div(self.skin_families_num as usize)
+                .unwrap_or(0) as u32,
+        );
+        header.skin_families_num = U32::new(self.skin_families_num);
+        header.skin_offset = push(self.skins.as_bytes());
+
+        let verts_offset = push(self.vertices.as_bytes());
+        let vert_info_offset = push(&self.vertex_bones);
+        let norms_offset = push(self.normals.as_bytes());
+        let norm_info_offset = push(&self.normal_bones);
+        let tris_offset = push(self.tricmds.as_bytes());
+        let mesh = Mesh {
+            tris: table(3, tris_offset),
+            skin_ref: I32::new(0),
+            norms: table(self.normals.len(), norms_offset),
+        };
+        let model = Model {
+            name: studio_name("body_ref"),
+            ty: I32::new(0),
+            bounding_radius: F32::new(1.0),
+            meshes: table(1, push(mesh.as_bytes())),
+            verts_num: U32::new(self.vertices.len() as u32),
+            vert_info_offset,
+            verts_offset,
+            norms_num: U32::new(self.normals.len() as u32),
+            norm_info_offset,
+            norms_offset,
+            groups: table(0, U32::new(0)),
+        };
+
+        let mut bodyparts = Vec::new();
+        let mut base = 1;
+        for (bodypart_id, &models_num) in self.bodyparts.iter().enumerate() {
+            let models = vec![model.clone(); models_num as usize];
+            bodyparts.push(BodyPart {
+                name: studio_name(&format!("part{bodypart_id}")),
+                models_num: U32::new(models_num),
+                base: I32::new(base),
+                models_offset: push(models.as_bytes()),
+            });
+            base *= models_num.max(1) as i32;
+        }
+        header.bodyparts = table(bodyparts.len(), push(bodyparts.as_bytes()));
+
+        header.length = U32::new(bytes.len() as u32);
+        bytes[..size_of::<MdlHeader>()].copy_from_slice(header.as_bytes());
+        bytes
+    }
+
+    /// Sequence group file holding the animations of a grouped model.
+    fn group_file(&self) -> Vec<u8> {
+        let header = SequenceGroupHeader {
+            magic: SEQUENCE_GROUP_MAGIC,
+            version: U32::new(MDL_VERSION),
+            name: studio_name("test01.mdl"),
+            length: U32::new((size_of::<SequenceGroupHeader>() + self.anim.len()) as u32),
+        };
+        let mut bytes = header.as_bytes().to_vec();
+        bytes.extend_from_slice(&self.anim);
+        bytes
+    }
+}
+
+fn studio_name<const N: usize>(name: &str) -> [u8; N] {
+    let mut bytes = [0; N];
+    bytes[..name.len()].copy_from_slice(name.as_bytes());
+    bytes
+}
@@ -0,0 +1,107 @@
+use goldsrc_rs::{
+    bsp::level,
+    error::ParsingError,
+    nod::{
+        DistInfo, GRAPH_VERSION, GraphHeader, GraphLink, GraphNode, LINK_HUMAN_HULL, NODE_LAND,
+        node_graph, node_leaves, node_links,
+    },
+};
+use zerocopy::{
+    FromZeros, IntoBytes,
+    little_endian::{F32, I16, I32, U32},
+};
+
+#[test]
+fn parse_nod() {
+    for path in glob::glob("./valve/maps/graphs/*.nod")
+        .expect("error globing nod")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let graph = node_graph(&data).unwrap();
+
+        println!("Nodes: {}", graph.nodes.len());
+        println!("Links: {}", graph.links.len());
+        for node in graph.nodes {
+            node_links(&graph, node).unwrap();
+        }
+
+        let bsp_path = path
+            .parent()
+            .and_then(|dir| dir.parent())
+            .zip(path.file_stem())
+            .map(|(dir, name)| dir.join(name).with_extension("bsp"));
+        if let Some(Ok(bsp)) = bsp_path.map(std::fs::read) {
+            let level = level(&bsp).unwrap();
+            let leaves = node_leaves(&level, &graph).unwrap();
+            assert_eq!(leaves.len(), graph.nodes.len());
+        }
+    }
+}
+
+#[test]
+fn parse_synthetic_nod() {
+    let mut header = GraphHeader::new_zeroed();
+    header.nodes_num = I32::new(2);
+    header.links_num = I32::new(2);
+    header.route_info_size = I32::new(3);
+    header.hash_links_num = I32::new(1);
+
+    let nodes: Vec<GraphNode> = (0..2)
+        .map(|i| {
+            let mut node = GraphNode::new_zeroed();
+            node.origin = [i as f32 * 64.0, 0.0, 0.0].map(F32::new);
+            node.info = U32::new(NODE_LAND);
+            node.links_num = I32::new(1);
+            node.first_link = I32::new(i);
+            node
+        })
+        .collect();
+    let links: Vec<GraphLink> = (0..2)
+        .map(|i| {
+            let mut link = GraphLink::new_zeroed();
+            link.src_node = I32::new(i);
+            link.dest_node = I32::new(1 - i);
+            link.info = U32::new(LINK_HUMAN_HULL);
+            link.weight = F32::new(64.0);
+            link
+        })
+        .collect();
+
+    let mut data = U32::new(GRAPH_VERSION).as_bytes().to_vec();
+    data.extend_from_slice(header.as_bytes());
+    data.extend_from_slice(nodes.as_bytes());
+    data.extend_from_slice(links.as_bytes());
+    data.extend_from_slice([DistInfo::new_zeroed(), DistInfo::new_zeroed()].as_bytes());
+    data.extend_from_slice(&[1, 2, 3]);
+    data.extend_from_slice(I16::new(-1).as_bytes());
+
+    let graph = node_graph(&data).unwrap();
+    assert_eq!(graph.nodes.len(), 2);
+    assert_eq!(graph.links.len(), 2);
+    assert_eq!(graph.dist_info.len(), 2);
+    assert_eq!(graph.route_info, [1, 2, 3]);
+    assert_eq!(graph.hash_links[0].get(), -1);
+    assert_eq!(graph.nodes[1].origin[0].get(), 64.0);
+
+    let links = node_links(&graph, &graph.nodes[1]).unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].src_node.get(), 1);
+    assert_eq!(links[0].dest_node.get(), 0);
+    assert_eq!(links[0].info.get(), LINK_HUMAN_HULL);
+
+    let mut node = graph.nodes[1].clone();
+    node.first_link = I32::new(2);
+    assert!(node_links(&graph, &node).is_err());
+
+    assert!(node_graph(&data[..data.len() - 1]).is_err());
+    data[0] = 15;
+    assert!(matches!(
+        node_graph(&data),
+        Err(ParsingError::WrongVersion {
+            got: 15,
+            expected: GRAPH_VERSION
+        })
+    ));
+}
@@ -0,0 +1,94 @@
+use goldsrc_rs::{
+    bsp::level,
+    error::ParsingError,
+    pointfile::{pointfile, trail_leaves},
+    prt::portal_file,
+};
+
+#[test]
+fn parse_prt() {
+    for path in glob::glob("./valve/maps/*.prt")
+        .expect("error globing prt")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let portals = portal_file(&data).unwrap();
+
+        println!("Leaves: {}", portals.leaves_num);
+        println!("Portals: {}", portals.portals.len());
+
+        if let Ok(bsp) = std::fs::read(path.with_extension("bsp")) {
+            let level = level(&bsp).unwrap();
+            portals.validate(&level).unwrap();
+        }
+    }
+}
+
+#[test]
+fn parse_synthetic_prt() {
+    let data = b"PRT1\n3\n2\n\
+        4 0 1 (0 -64 0 ) (0 64 0 ) (0 64 128 ) (0 -64 128 )\n\
+        3 1 2 (64 0 0 ) (64 64 0 ) (64 0 64 )\n";
+    let portals = portal_file(data).unwrap();
+
+    assert_eq!(portals.leaves_num, 3);
+    assert_eq!(portals.portals.len(), 2);
+    assert_eq!(portals.portals[0].leaves, [0, 1]);
+    assert_eq!(portals.portals[0].level_leaves(), [1, 2]);
+    assert_eq!(
+        portals.portals[0].winding,
+        [
+            [0.0, -64.0, 0.0],
+            [0.0, 64.0, 0.0],
+            [0.0, 64.0, 128.0],
+            [0.0, -64.0, 128.0]
+        ]
+    );
+    assert_eq!(portals.portals[1].winding[2], [64.0, 0.0, 64.0]);
+
+    assert!(matches!(
+        portal_file(b"PRT2\n1\n0\n"),
+        Err(ParsingError::WrongFourCC { .. })
+    ));
+    assert!(portal_file(b"PRT1\n2\n1\n3 0 2 (0 0 0) (0 1 0) (1 0 0)\n").is_err());
+    assert!(portal_file(b"PRT1\n2\n1\n3 0 1 (0 0 0) (0 1 0)\n").is_err());
+}
+
+#[test]
+fn parse_pointfile() {
+    for path in glob::glob("./valve/maps/*.pts")
+        .expect("error globing pts")
+        .chain(glob::glob("./valve/maps/*.lin").expect("error globing lin"))
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let points = pointfile(&data).unwrap();
+
+        println!("Points: {}", points.len());
+
+        if let Ok(bsp) = std::fs::read(path.with_extension("bsp")) {
+            let level = level(&bsp).unwrap();
+            let leaves = trail_leaves(&level, &points).unwrap();
+            assert_eq!(leaves.len(), points.len());
+        }
+    }
+}
+
+#[test]
+fn parse_synthetic_pointfile() {
+    let points = pointfile(b"0 0 16\n0 0 32\n\n0 0 32 - 64 0 32\n64 0 32 - 64 64 32\n").unwrap();
+    assert_eq!(
+        points,
+        [
+            [0.0, 0.0, 16.0],
+            [0.0, 0.0, 32.0],
+            [64.0, 0.0, 32.0],
+            [64.0, 64.0, 32.0]
+        ]
+    );
+
+    assert!(pointfile(b"0 0\n").is_err());
+    assert!(pointfile(b"0 0 x\n").is_err());
+}
@@ -0,0 +1,115 @@
+use goldsrc_rs::{
+    bsp::{BSP_VERSION, LUMP_ENTITIES, LUMP_TEXTURES, LevelHeader, level},
+    common::Lump,
+    detail::detail_textures,
+    res::{ResourceList, map_resources},
+};
+use zerocopy::{IntoBytes, little_endian::U32};
+
+#[test]
+fn generate_res() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+
+        let map_name = path.file_stem().unwrap().to_string_lossy();
+        let detail_data = std::fs::read(path.with_file_name(format!("{map_name}_detail.txt")))
+            .unwrap_or_default();
+        let details = detail_textures(&detail_data).unwrap();
+
+        let list = map_resources(&level, &map_name, &details).unwrap();
+        let mut res = Vec::new();
+        list.write_res(&mut res).unwrap();
+        println!("Resources: {}", list.resources.len());
+    }
+}
+
+/// Level with only an entity lump and an empty texture lump.
+fn entity_level(entities: &str) -> Vec<u8> {
+    let header_size = size_of::<LevelHeader>() as u32;
+    let mut header = LevelHeader {
+        version: U32::new(BSP_VERSION),
+        lumps: std::array::from_fn(|_| Lump {
+            offset: U32::new(header_size),
+            size: U32::new(0),
+        }),
+    };
+    header.lumps[LUMP_TEXTURES].size = U32::new(4);
+    header.lumps[LUMP_ENTITIES].offset = U32::new(header_size + 4);
+    header.lumps[LUMP_ENTITIES].size = U32::new(entities.len() as u32);
+
+    let mut bytes = header.as_bytes().to_vec();
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(entities.as_bytes());
+    bytes
+}
+
+#[test]
+fn synthetic_res() {
+    let data = entity_level(concat!(
+        "{\n\"classname\" \"worldspawn\"\n\"skyname\" \"desert\"\n",
+        "\"wad\" \"\\half-life\\valve\\halflife.wad;c:/mods/custom.wad;\"\n}\n",
+        "{\n\"classname\" \"ambient_generic\"\n\"message\" \"*ambience/Drips.wav\"\n}\n",
+        "{\n\"classname\" \"env_sprite\"\n\"model\" \"sprites\\glow01.spr\"\n}\n",
+        "{\n\"classname\" \"monster_barney\"\n\"model\" \"models/barney.MDL\"\n}\n",
+        "{\n\"classname\" \"monster_barney\"\n\"model\" \"/Models/Barney.mdl\"\n}\n",
+        "{\n\"classname\" \"info_player_start\"\n\"origin\" \"0 0 36\"\n}\n",
+    ));
+    let level = level(&data).unwrap();
+    let details = detail_textures(b"floor detail/metal1 2 2\n").unwrap();
+
+    let list = map_resources(&level, "test", &details).unwrap();
+    assert_eq!(
+        list.resources,
+        [
+            "halflife.wad",
+            "custom.wad",
+            "sound/ambience/Drips.wav",
+            "sprites/glow01.spr",
+            "models/barney.MDL",
+            "gfx/env/desertrt.tga",
+            "gfx/env/desertbk.tga",
+            "gfx/env/desertlf.tga",
+            "gfx/env/desertft.tga",
+            "gfx/env/desertup.tga",
+            "gfx/env/desertdn.tga",
+            "maps/test_detail.txt",
+            "gfx/detail/metal1.tga",
+        ]
+    );
+
+    let mut res = Vec::new();
+    list.write_res(&mut res).unwrap();
+    assert!(res.starts_with(b"halflife.wad\ncustom.wad\nsound/ambience/Drips.wav\n"));
+    assert_eq!(res.iter().filter(|&&b| b == b'\n').count(), 13);
+}
+
+#[test]
+fn exclude_existing_res() {
+    let base_dir = std::env::temp_dir().join(format!("goldsrc-rs-res-{}", std::process::id()));
+    std::fs::create_dir_all(base_dir.join("Models")).unwrap();
+    std::fs::create_dir_all(base_dir.join("sound/ambience")).unwrap();
+    std::fs::write(base_dir.join("Models/Barney.mdl"), b"").unwrap();
+    std::fs::write(base_dir.join("sound/ambience/drips.wav"), b"").unwrap();
+
+    let mut list = ResourceList::default();
+    for path in [
+        "models/barney.mdl",
+        "SOUND/Ambience/Drips.wav",
+        "sprites/glow01.spr",
+        "models/barney/head.mdl",
+    ] {
+        list.add(path);
+    }
+    list.exclude_existing(&base_dir);
+    std::fs::remove_dir_all(&base_dir).unwrap();
+
+    assert_eq!(
+        list.resources,
+        ["sprites/glow01.spr", "models/barney/head.mdl"]
+    );
+}
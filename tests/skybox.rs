@@ -0,0 +1,141 @@
+use goldsrc_rs::{
+    bsp::{entities::entities, level},
+    error::ParsingError,
+    skybox::{
+        BMP_MAGIC, BmpHeader, TGA_TYPE_TRUECOLOR, TGA_TYPE_TRUECOLOR_RLE, TgaHeader, bmp_image,
+        sky_name, skybox, tga_image,
+    },
+};
+use zerocopy::{
+    FromZeros, IntoBytes,
+    little_endian::{I32, U16, U32},
+};
+
+#[test]
+fn load_skybox() {
+    for path in glob::glob("./valve/maps/*.bsp")
+        .expect("error globing bsp")
+        .flatten()
+    {
+        println!("File: {:?}", path);
+        let data = std::fs::read(&path).expect("error reading file");
+        let level = level(&data).unwrap();
+
+        let Some(sky) = skybox(&level, |name| std::fs::read(format!("./valve/{name}")).ok())
+            .unwrap_or_else(|err| panic!("error loading skybox: {err}"))
+        else {
+            continue;
+        };
+        println!("Sky: {}", String::from_utf8_lossy(sky.name));
+        for face in &sky.faces {
+            assert_eq!(
+                face.image.pixels.len(),
+                (face.image.width * face.image.height) as usize
+            );
+        }
+    }
+}
+
+fn tga_header(image_type: u8, pixel_depth: u8, descriptor: u8, size: u16) -> TgaHeader {
+    let mut header = TgaHeader::new_zeroed();
+    header.image_type = image_type;
+    header.pixel_depth = pixel_depth;
+    header.descriptor = descriptor;
+    header.width = U16::new(size);
+    header.height = U16::new(size);
+    header
+}
+
+fn bmp_header(size: i32, data_offset: u32) -> BmpHeader {
+    let mut header = BmpHeader::new_zeroed();
+    header.magic = BMP_MAGIC;
+    header.data_offset = U32::new(data_offset);
+    header.info_size = U32::new(40);
+    header.width = I32::new(size);
+    header.height = I32::new(size);
+    header.planes = U16::new(1);
+    header.bit_count = U16::new(8);
+    header.colors_used = U32::new(2);
+    header
+}
+
+#[test]
+fn decode_tga() {
+    // Bottom-up BGR rows: red, green, then blue, white.
+    let mut data = tga_header(TGA_TYPE_TRUECOLOR, 24, 0, 2).as_bytes().to_vec();
+    data.extend_from_slice(&[0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255]);
+    let image = tga_image(&data).unwrap();
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(
+        image.pixels,
+        [
+            [0, 0, 255, 255],
+            [255, 255, 255, 255],
+            [255, 0, 0, 255],
+            [0, 255, 0, 255]
+        ]
+    );
+    assert!(matches!(
+        tga_image(&data[..data.len() - 1]),
+        Err(ParsingError::OutOfRange(_))
+    ));
+
+    // Top-down BGRA run of four pixels.
+    let mut data = tga_header(TGA_TYPE_TRUECOLOR_RLE, 32, 0x20, 2)
+        .as_bytes()
+        .to_vec();
+    data.extend_from_slice(&[0x83, 10, 20, 30, 40]);
+    let image = tga_image(&data).unwrap();
+    assert_eq!(image.pixels, [[30, 20, 10, 40]; 4]);
+
+    // Huge images are rejected before their pixels are allocated.
+    let data = tga_header(TGA_TYPE_TRUECOLOR, 32, 0, u16::MAX);
+    assert!(matches!(
+        tga_image(data.as_bytes()),
+        Err(ParsingError::OutOfRange(_))
+    ));
+    let mut data = tga_header(TGA_TYPE_TRUECOLOR_RLE, 32, 0, u16::MAX)
+        .as_bytes()
+        .to_vec();
+    data.extend_from_slice(&[0xFF, 1, 2, 3, 4]);
+    assert!(matches!(tga_image(&data), Err(ParsingError::OutOfRange(_))));
+}
+
+#[test]
+fn decode_bmp() {
+    // Two color palette (blue, red) and bottom-up rows padded to 4 bytes.
+    let mut data = bmp_header(2, 62).as_bytes().to_vec();
+    data.extend_from_slice(&[255, 0, 0, 0, 0, 0, 255, 0]);
+    data.extend_from_slice(&[0, 1, 0, 0, 1, 0, 0, 0]);
+    let image = bmp_image(&data).unwrap();
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(
+        image.pixels,
+        [
+            [255, 0, 0, 255],
+            [0, 0, 255, 255],
+            [0, 0, 255, 255],
+            [255, 0, 0, 255]
+        ]
+    );
+
+    let mut bad_index = data.clone();
+    bad_index[62] = 2;
+    assert!(bmp_image(&bad_index).is_err());
+
+    let mut data = bmp_header(i32::from(u16::MAX), 62).as_bytes().to_vec();
+    data.extend_from_slice(&[0; 16]);
+    assert!(matches!(bmp_image(&data), Err(ParsingError::OutOfRange(_))));
+}
+
+#[test]
+fn worldspawn_sky_name() {
+    let named = entities(
+        b"{\n\"classname\" \"worldspawn\"\n\"skyname\" \"desert\"\n}\n{\n\"classname\" \"light\"\n}\n",
+    )
+    .unwrap();
+    assert_eq!(sky_name(&named), Some(&b"desert"[..]));
+
+    let unnamed = entities(b"{\n\"classname\" \"worldspawn\"\n\"skyname\" \"\"\n}\n").unwrap();
+    assert_eq!(sky_name(&unnamed), None);
+}